@@ -0,0 +1,36 @@
+use cryptopals::modular::ModContext;
+use num_bigint::{BigUint, RandBigInt};
+use std::time::Instant;
+
+// ModContext::pow against BigUint::modpow for odd moduli of a few sizes, the
+// residues are made once up front as they are in MontGroup
+fn main() {
+    let mut rng = rand::thread_rng();
+    for (bits, rounds) in [(256, 2000), (512, 1000), (1024, 200), (2048, 40)] {
+        let n = rng.gen_biguint(bits) | BigUint::from(1u8) | BigUint::from(1u8) << (bits - 1);
+        let ctx = ModContext::new(&n).expect("n is odd");
+        let cases: Vec<_> = (0..rounds)
+            .map(|_| (rng.gen_biguint_below(&n), rng.gen_biguint(bits)))
+            .collect();
+        let residues: Vec<_> = cases.iter().map(|(a, _)| ctx.residue(a)).collect();
+
+        let start = Instant::now();
+        let expected: Vec<_> = cases.iter().map(|(a, e)| a.modpow(e, &n)).collect();
+        let modpow = start.elapsed();
+        let start = Instant::now();
+        let got: Vec<_> = residues
+            .iter()
+            .zip(&cases)
+            .map(|(a, (_, e))| ctx.pow(a, e))
+            .collect();
+        let pow = start.elapsed();
+
+        assert!(got.iter().zip(&expected).all(|(x, y)| ctx.value(x) == *y));
+        println!(
+            "{bits:>5} bits: modpow {:>8.1?} pow {:>8.1?} per op, {:.2}x",
+            modpow / rounds,
+            pow / rounds,
+            modpow.as_secs_f64() / pow.as_secs_f64()
+        );
+    }
+}
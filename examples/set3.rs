@@ -1,6 +1,6 @@
 use cryptopals::aes::{aes, inv_aes};
 use cryptopals::block::{
-    cbc_encr, check_cbc_padding, decrypt_cbc_oracle, pad_pkcs7, rand_key, unpad_pkcs7,
+    cbc_encr, check_cbc_padding_insecure, decrypt_cbc_oracle, pad_pkcs7, rand_key, unpad_pkcs7,
 };
 use cryptopals::encode::{block_from_ascii, from_base64, to_ascii};
 use cryptopals::stream::{break_ctr_blocks, ctr, get_mt19937_seed, mt19937};
@@ -32,7 +32,7 @@ fn ex17() -> String {
     let encr = cbc_encr(&input, &key, &iv, aes).expect("should have been padded");
     pad_pkcs7(input, N as u8);
 
-    let oracle = |v: &[u8; N], iv: &[u8; N]| check_cbc_padding(v, &key, iv, inv_aes);
+    let oracle = |v: &[u8; N], iv: &[u8; N]| check_cbc_padding_insecure(v, &key, iv, inv_aes);
     let v = decrypt_cbc_oracle(&encr, &iv, &oracle).and_then(unpad_pkcs7);
     to_ascii(&v.expect("should have been padded"))
 }
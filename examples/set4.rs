@@ -1,9 +1,16 @@
 use cryptopals::aes::{aes, inv_aes};
 use cryptopals::block::{ecb, rand_key};
-use cryptopals::encode::{block_from_ascii, from_base64, to_ascii};
+use cryptopals::digest::{hmac, length_extension, Hasher, Md4, Sha1};
+use cryptopals::dist::median;
+use cryptopals::encode::{block_from_ascii, from_base64, from_hex, to_ascii, to_hex};
 use cryptopals::stream::{ctr, decrypt_ctr, edit_block};
+use cryptopals::timing::{insecure_compare, recover_mac};
 use rand::Rng;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
 
 fn ex25() -> String {
     let file = fs::read_to_string("data/25.txt").expect("file missing");
@@ -21,7 +28,85 @@ fn ex25() -> String {
     to_ascii(&decr)
 }
 
+fn ex29_30<H: Hasher<N>, const N: usize>() -> String {
+    let mut rng = rand::thread_rng();
+    let key: [u8; 16] = rand_key(&mut rng);
+    let msg = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = H::digest(&[&key[..], msg].concat());
+    let verify = |m: &[u8], d: &[u8; N]| &H::digest(&[&key[..], m].concat()) == d;
+    let (forged, _) = length_extension::<H, N>(&mac, msg, b";admin=true", 64, verify)
+        .expect("secret length not found");
+    to_ascii(&forged)
+}
+
+// GET /test?file=...&signature=... HTTP/1.1
+fn parse_request(line: &str) -> Option<(&str, &str)> {
+    let query = line.split_whitespace().nth(1)?.split_once('?')?.1;
+    let mut file = None;
+    let mut signature = None;
+    for param in query.split('&') {
+        match param.split_once('=')? {
+            ("file", f) => file = Some(f),
+            ("signature", s) => signature = Some(s),
+            _ => {}
+        }
+    }
+    Some((file?, signature?))
+}
+
+fn serve(listener: TcpListener, key: [u8; 16], delay: Duration) {
+    for stream in listener.incoming() {
+        let mut stream = stream.expect("connection failed");
+        let mut line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut line)
+            .expect("invalid request");
+        let valid = parse_request(&line).is_some_and(|(file, sig)| {
+            let mac = hmac::<Sha1, 20>(&key, file.as_bytes());
+            insecure_compare(&from_hex(sig), &mac, delay)
+        });
+        let status = match valid {
+            true => "200 OK",
+            false => "500 Internal Server Error",
+        };
+        let resp = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+        stream.write_all(resp.as_bytes()).expect("write failed");
+    }
+}
+
+fn request(addr: SocketAddr, file: &str, sig: &[u8; 20]) -> bool {
+    let mut stream = TcpStream::connect(addr).expect("connection failed");
+    let req = format!(
+        "GET /test?file={}&signature={} HTTP/1.1\r\n\r\n",
+        file,
+        to_hex(sig)
+    );
+    stream.write_all(req.as_bytes()).expect("write failed");
+    let mut line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut line)
+        .expect("invalid response");
+    line.split_whitespace().nth(1) == Some("200")
+}
+
+fn ex31_32(delay: Duration, samples: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let key = rand_key(&mut rng);
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind failed");
+    let addr = listener.local_addr().expect("no local address");
+    thread::spawn(move || serve(listener, key, delay));
+
+    let file = "foo";
+    let query = |sig: &[u8; 20]| request(addr, file, sig);
+    let mac = recover_mac(query, samples, samples * 32, median).expect("mac not found");
+    assert_eq!(mac, hmac::<Sha1, 20>(&key, file.as_bytes()));
+    to_hex(&mac)
+}
 
 fn main() {
     println!("ex25:\n{}", ex25().chars().take(200).collect::<String>());
+    println!("ex29:\n{}", ex29_30::<Sha1, 20>());
+    println!("ex30:\n{}", ex29_30::<Md4, 16>());
+    println!("ex31:\n{}", ex31_32(Duration::from_millis(5), 1));
+    println!("ex32:\n{}", ex31_32(Duration::from_micros(50), 5));
 }
@@ -1,21 +1,14 @@
 use cryptopals::encode::{from_ascii, to_ascii};
-use cryptopals::rsa::{decrypt_rsa_n, rsa_keys, rsa_msg};
+use cryptopals::rsa::{decrypt_rsa_n, rsa_msg, RsaPrivateKey};
 use num_bigint::BigUint;
 
 fn ex40(input: &str) -> String {
     let msg = from_ascii(input);
     // get n pubkeys
-    let mut keys = [1, 2, 3, 4, 5].map(|_| (0u8.into()));
-    let pubk = 5;
-    let mut i = 0;
+    let pubk = 5u8;
     let mut rng = rand::thread_rng();
-    while i < pubk {
-        let (pub_key, _, n) = rsa_keys(msg.len() as u64 * 8, &mut rng);
-        if pub_key == pubk.into() {
-            keys[i] = n;
-            i += 1;
-        }
-    }
+    let bits = msg.len() as u64 * 8;
+    let keys = [(); 5].map(|_| RsaPrivateKey::generate(bits, &pubk.into(), &mut rng).n);
     // encrypt same message n times
     let ciphers = keys
         .clone()
@@ -1,4 +1,5 @@
-use cryptopals::math::pollard_lambda;
+use cryptopals::group::MontGroup;
+use cryptopals::math::kangaroo;
 use num_bigint::BigUint;
 use std::time::Instant;
 
@@ -11,13 +12,19 @@ fn ex() {
     //let j = &BigUint::parse_bytes(b"34233586850807404623475048381328686211071196701374230492615844865929237417097514638999377942356150481334217896204702", 10).expect("not a number");
     let y = &BigUint::parse_bytes(b"9388897478013399550694114614498790691034187453089355259602614074132918843899833277397448144245883225611726912025846772975325932794909655215329941809013733", 10).expect("not a number");
     let upper = 40;
-    let k = upper as u8 >> 1;
-    for c in 2..15 {
-        let start = Instant::now();
-        let x = pollard_lambda(p, g, y, &0u8.into(), &2usize.pow(upper).into(), k, c);
-        let dur = start.elapsed();
-        println!("{:?} {} {}", x, c, dur.as_millis());
-    }
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let start = Instant::now();
+    let progress = |jumps: u64, expected: u64| {
+        if jumps % (1 << 20) < 1 << 14 {
+            println!("{jumps} of about {expected} jumps");
+        }
+    };
+    let group = MontGroup::new(p, &(p - 1u8));
+    let (g, y) = (&group.ctx.residue(g), &group.ctx.residue(y));
+    let ul = 2usize.pow(upper).into();
+    let x = kangaroo(&group, g, y, &0u8.into(), &ul, threads, progress);
+    let dur = start.elapsed();
+    println!("{:?} {}", x, dur.as_millis());
 }
 
 fn main() {
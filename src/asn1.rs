@@ -0,0 +1,470 @@
+use num_bigint::BigUint;
+use std::fmt;
+
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+const CONTEXT: u8 = 0x80;
+const CONSTRUCTED: u8 = 0x20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Asn1 {
+    Integer(BigUint),
+    // unused bits in the last byte, bytes
+    BitString(u8, Vec<u8>),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Vec<u64>),
+    Sequence(Vec<Asn1>),
+    Set(Vec<Asn1>),
+    // [n] EXPLICIT, wraps a complete element
+    Explicit(u8, Box<Asn1>),
+    // [n] IMPLICIT of a primitive type, the content octets are kept as is
+    Implicit(u8, Vec<u8>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    // distinguished encoding, exactly one valid encoding per value
+    Der,
+    // lenient, accepts non-minimal lengths and integers and indefinite lengths
+    Ber,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Asn1Error {
+    Truncated,
+    InvalidLength,
+    NonMinimalLength,
+    IndefiniteLength,
+    UnsupportedTag(u8),
+    NonMinimalInteger,
+    NegativeInteger,
+    InvalidBitString,
+    InvalidNull,
+    InvalidOid,
+    TrailingData,
+    UnexpectedType,
+    TooDeep,
+}
+
+impl fmt::Display for Asn1Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid asn.1: {:?}", self)
+    }
+}
+
+impl std::error::Error for Asn1Error {}
+
+type Result<T> = std::result::Result<T, Asn1Error>;
+
+// how deeply constructed elements may nest before decoding gives up, far more
+// than any key needs and far less than overflows the stack
+const MAX_DEPTH: usize = 64;
+
+fn encode_len(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes: Vec<_> = len
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|b| *b == 0)
+        .collect();
+    let mut v = vec![0x80 | bytes.len() as u8];
+    v.extend(bytes);
+    v
+}
+
+pub fn encode_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut v = vec![tag];
+    v.extend(encode_len(content.len()));
+    v.extend(content);
+    v
+}
+
+fn encode_arc(mut x: u64) -> Vec<u8> {
+    let mut v = vec![(x & 0x7f) as u8];
+    x >>= 7;
+    while x > 0 {
+        v.push(0x80 | (x & 0x7f) as u8);
+        x >>= 7;
+    }
+    v.reverse();
+    v
+}
+
+impl Asn1 {
+    // always der, an error for the values decode would not give back: an oid
+    // with no encoding, a bit string with bad unused bits and tags above 30,
+    // which need the long form
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let children = |v: &[Asn1]| v.iter().map(Asn1::encode).collect::<Result<Vec<_>>>();
+        let tag = |class: u8, n: u8| match n {
+            0..=30 => Ok(class | n),
+            _ => Err(Asn1Error::UnsupportedTag(class | 0x1f)),
+        };
+        Ok(match self {
+            Asn1::Integer(x) => {
+                // positive integers get a leading zero when the high bit is set
+                let mut content = x.to_bytes_be();
+                if content[0] & 0x80 != 0 {
+                    content.insert(0, 0);
+                }
+                encode_tlv(INTEGER, &content)
+            }
+            Asn1::BitString(unused, bytes) => {
+                let last = bytes.last().copied().unwrap_or(0);
+                if *unused > 7
+                    || (bytes.is_empty() && *unused > 0)
+                    || last & ((1 << unused) - 1) != 0
+                {
+                    return Err(Asn1Error::InvalidBitString);
+                }
+                let mut content = vec![*unused];
+                content.extend(bytes);
+                encode_tlv(BIT_STRING, &content)
+            }
+            Asn1::OctetString(bytes) => encode_tlv(OCTET_STRING, bytes),
+            Asn1::Null => encode_tlv(NULL, &[]),
+            Asn1::Oid(arcs) => {
+                // the first two arcs share a byte, so the second is below 40
+                // unless the first is 2
+                let (first, rest) = match arcs.as_slice() {
+                    [a @ 0..=1, b @ 0..=39, rest @ ..] => (a * 40 + b, rest),
+                    [2, b, rest @ ..] => (b.checked_add(80).ok_or(Asn1Error::InvalidOid)?, rest),
+                    _ => return Err(Asn1Error::InvalidOid),
+                };
+                let mut content = encode_arc(first);
+                content.extend(rest.iter().flat_map(|a| encode_arc(*a)));
+                encode_tlv(OID, &content)
+            }
+            Asn1::Sequence(v) => encode_tlv(SEQUENCE, &children(v)?.concat()),
+            Asn1::Set(v) => encode_tlv(SET, &children(v)?.concat()),
+            Asn1::Explicit(n, x) => encode_tlv(tag(CONTEXT | CONSTRUCTED, *n)?, &x.encode()?),
+            Asn1::Implicit(n, bytes) => encode_tlv(tag(CONTEXT, *n)?, bytes),
+        })
+    }
+
+    pub fn as_integer(&self) -> Result<&BigUint> {
+        match self {
+            Asn1::Integer(x) => Ok(x),
+            _ => Err(Asn1Error::UnexpectedType),
+        }
+    }
+
+    pub fn as_sequence(&self) -> Result<&[Asn1]> {
+        match self {
+            Asn1::Sequence(v) => Ok(v),
+            _ => Err(Asn1Error::UnexpectedType),
+        }
+    }
+
+    pub fn as_octet_string(&self) -> Result<&[u8]> {
+        match self {
+            Asn1::OctetString(v) => Ok(v),
+            _ => Err(Asn1Error::UnexpectedType),
+        }
+    }
+
+    // only bit strings without unused bits, which is how keys are wrapped
+    pub fn as_bit_string(&self) -> Result<&[u8]> {
+        match self {
+            Asn1::BitString(0, v) => Ok(v),
+            _ => Err(Asn1Error::UnexpectedType),
+        }
+    }
+
+    pub fn as_oid(&self) -> Result<&[u64]> {
+        match self {
+            Asn1::Oid(v) => Ok(v),
+            _ => Err(Asn1Error::UnexpectedType),
+        }
+    }
+}
+
+// tag, content length (None when indefinite) and the bytes after the header
+fn decode_header(bytes: &[u8], mode: Mode) -> Result<(u8, Option<usize>, &[u8])> {
+    let (&tag, rest) = bytes.split_first().ok_or(Asn1Error::Truncated)?;
+    if tag & 0x1f == 0x1f {
+        return Err(Asn1Error::UnsupportedTag(tag));
+    }
+    let (&l, rest) = rest.split_first().ok_or(Asn1Error::Truncated)?;
+    match l {
+        0..=0x7f => Ok((tag, Some(l as usize), rest)),
+        0x80 if mode == Mode::Ber && tag & CONSTRUCTED != 0 => Ok((tag, None, rest)),
+        0x80 => Err(Asn1Error::IndefiniteLength),
+        0x81..=0x88 => {
+            let n = (l & 0x7f) as usize;
+            let len_bytes = rest.get(..n).ok_or(Asn1Error::Truncated)?;
+            let len = len_bytes.iter().fold(0, |a, b| (a << 8) | *b as usize);
+            if mode == Mode::Der && (len_bytes[0] == 0 || len < 0x80) {
+                return Err(Asn1Error::NonMinimalLength);
+            }
+            Ok((tag, Some(len), &rest[n..]))
+        }
+        _ => Err(Asn1Error::InvalidLength),
+    }
+}
+
+fn decode_integer(content: &[u8], mode: Mode) -> Result<Asn1> {
+    match content {
+        [] => Err(Asn1Error::Truncated),
+        [b, ..] if b & 0x80 != 0 => Err(Asn1Error::NegativeInteger),
+        [0, b, ..] if b & 0x80 == 0 && mode == Mode::Der => Err(Asn1Error::NonMinimalInteger),
+        _ => Ok(Asn1::Integer(BigUint::from_bytes_be(content))),
+    }
+}
+
+fn decode_bit_string(content: &[u8], mode: Mode) -> Result<Asn1> {
+    let (&unused, bytes) = content.split_first().ok_or(Asn1Error::Truncated)?;
+    if unused > 7 || (bytes.is_empty() && unused > 0) {
+        return Err(Asn1Error::InvalidBitString);
+    }
+    let last = bytes.last().copied().unwrap_or(0);
+    if mode == Mode::Der && last & ((1 << unused) - 1) != 0 {
+        return Err(Asn1Error::InvalidBitString);
+    }
+    Ok(Asn1::BitString(unused, Vec::from(bytes)))
+}
+
+fn decode_oid(content: &[u8], mode: Mode) -> Result<Asn1> {
+    let mut arcs = vec![];
+    let mut x: u64 = 0;
+    let mut start = true;
+    for b in content {
+        if start && *b == 0x80 && mode == Mode::Der {
+            return Err(Asn1Error::InvalidOid);
+        }
+        x = x.checked_mul(128).ok_or(Asn1Error::InvalidOid)? | (b & 0x7f) as u64;
+        start = b & 0x80 == 0;
+        if start {
+            arcs.push(x);
+            x = 0;
+        }
+    }
+    if !start || arcs.is_empty() {
+        return Err(Asn1Error::InvalidOid);
+    }
+    let first = arcs[0].min(80);
+    arcs[0] -= first;
+    arcs.insert(0, first / 40);
+    arcs[1] += first % 40;
+    Ok(Asn1::Oid(arcs))
+}
+
+fn decode_children(mut content: &[u8], mode: Mode, depth: usize) -> Result<Vec<Asn1>> {
+    let mut v = vec![];
+    while !content.is_empty() {
+        let (x, rest) = decode_nested(content, mode, depth)?;
+        v.push(x);
+        content = rest;
+    }
+    Ok(v)
+}
+
+// children of an indefinite-length element, up to the end-of-contents marker
+fn decode_indefinite(mut bytes: &[u8], mode: Mode, depth: usize) -> Result<(Vec<Asn1>, &[u8])> {
+    let mut v = vec![];
+    loop {
+        match bytes {
+            [0, 0, rest @ ..] => return Ok((v, rest)),
+            [] => return Err(Asn1Error::Truncated),
+            _ => {
+                let (x, rest) = decode_nested(bytes, mode, depth)?;
+                v.push(x);
+                bytes = rest;
+            }
+        }
+    }
+}
+
+// decodes the first element and returns it with the remaining bytes
+pub fn decode_prefix(bytes: &[u8], mode: Mode) -> Result<(Asn1, &[u8])> {
+    decode_nested(bytes, mode, 0)
+}
+
+// decode_prefix for an element inside depth others
+fn decode_nested(bytes: &[u8], mode: Mode, depth: usize) -> Result<(Asn1, &[u8])> {
+    if depth > MAX_DEPTH {
+        return Err(Asn1Error::TooDeep);
+    }
+    let (tag, len, rest) = decode_header(bytes, mode)?;
+    let (parsed, content, rest) = match len {
+        Some(len) => {
+            let content = rest.get(..len).ok_or(Asn1Error::Truncated)?;
+            (None, content, &rest[len..])
+        }
+        None => {
+            let (parsed, rest) = decode_indefinite(rest, mode, depth + 1)?;
+            (Some(parsed), &[][..], rest)
+        }
+    };
+    let children = |content| match parsed {
+        Some(v) => Ok(v),
+        None => decode_children(content, mode, depth + 1),
+    };
+    let x = match tag {
+        INTEGER => decode_integer(content, mode)?,
+        BIT_STRING => decode_bit_string(content, mode)?,
+        OCTET_STRING => Asn1::OctetString(Vec::from(content)),
+        NULL if content.is_empty() => Asn1::Null,
+        NULL => return Err(Asn1Error::InvalidNull),
+        OID => decode_oid(content, mode)?,
+        SEQUENCE => Asn1::Sequence(children(content)?),
+        SET => Asn1::Set(children(content)?),
+        0xa0..=0xbe => match <[Asn1; 1]>::try_from(children(content)?) {
+            Ok([x]) => Asn1::Explicit(tag & 0x1f, Box::new(x)),
+            Err(_) => return Err(Asn1Error::UnexpectedType),
+        },
+        0x80..=0x9e => Asn1::Implicit(tag & 0x1f, Vec::from(content)),
+        _ => return Err(Asn1Error::UnsupportedTag(tag)),
+    };
+    Ok((x, rest))
+}
+
+// decodes exactly one element
+pub fn decode(bytes: &[u8], mode: Mode) -> Result<Asn1> {
+    match decode_prefix(bytes, mode)? {
+        (x, []) => Ok(x),
+        _ => Err(Asn1Error::TrailingData),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::{from_hex, to_hex};
+    use rand::Rng;
+
+    #[test]
+    fn encode_integer_works() {
+        let enc = |x: u16| to_hex(&Asn1::Integer(x.into()).encode().expect("valid"));
+        assert_eq!(enc(0), "020100");
+        assert_eq!(enc(127), "02017f");
+        assert_eq!(enc(128), "02020080");
+        assert_eq!(enc(256), "02020100");
+    }
+
+    #[test]
+    fn oid_works() {
+        // rsaEncryption
+        let oid = Asn1::Oid(vec![1, 2, 840, 113549, 1, 1, 1]);
+        let der = oid.encode().expect("valid oid");
+        assert_eq!(to_hex(&der), "06092a864886f70d010101");
+        assert_eq!(decode(&der, Mode::Der), Ok(oid));
+        let oid = Asn1::Oid(vec![2, 999, 3]);
+        assert_eq!(
+            decode(&oid.encode().expect("valid oid"), Mode::Der),
+            Ok(oid)
+        );
+        for arcs in [vec![], vec![1], vec![1, 40], vec![3, 1], vec![2, u64::MAX]] {
+            let x = Asn1::Sequence(vec![Asn1::Oid(arcs)]);
+            assert_eq!(x.encode(), Err(Asn1Error::InvalidOid));
+        }
+    }
+
+    #[test]
+    fn decode_works() {
+        let x = BigUint::from(1u8) << 2000u16;
+        let seq = Asn1::Sequence(vec![
+            Asn1::Integer(x),
+            Asn1::Null,
+            Asn1::OctetString(vec![1, 2, 3]),
+            Asn1::BitString(4, vec![0xf0]),
+            Asn1::Explicit(0, Box::new(Asn1::Integer(3u8.into()))),
+            Asn1::Implicit(1, vec![7, 7]),
+            Asn1::Set(vec![Asn1::Null]),
+        ]);
+        let der = seq.encode().expect("valid");
+        assert_eq!(&der[..4], &[0x30, 0x82, 0x01, 0x16]);
+        assert_eq!(decode(&der, Mode::Der), Ok(seq.clone()));
+        assert_eq!(decode(&der, Mode::Ber), Ok(seq));
+        assert_eq!(
+            decode(&der[..der.len() - 1], Mode::Der),
+            Err(Asn1Error::Truncated)
+        );
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing, Mode::Der), Err(Asn1Error::TrailingData));
+    }
+
+    #[test]
+    fn encode_rejects_what_decode_would() {
+        let null = || Box::new(Asn1::Null);
+        let ok = [
+            Asn1::Explicit(30, null()),
+            Asn1::Implicit(30, vec![1]),
+            Asn1::BitString(7, vec![0x80]),
+            Asn1::BitString(0, vec![]),
+        ];
+        for x in ok {
+            let der = x.encode().expect("valid");
+            assert_eq!(decode(&der, Mode::Der), Ok(x));
+        }
+        let bad = [
+            (Asn1::Explicit(31, null()), Asn1Error::UnsupportedTag(0xbf)),
+            (Asn1::Explicit(40, null()), Asn1Error::UnsupportedTag(0xbf)),
+            (Asn1::Implicit(31, vec![1]), Asn1Error::UnsupportedTag(0x9f)),
+            (Asn1::BitString(9, vec![1]), Asn1Error::InvalidBitString),
+            (Asn1::BitString(1, vec![]), Asn1Error::InvalidBitString),
+            (Asn1::BitString(4, vec![0xf1]), Asn1Error::InvalidBitString),
+        ];
+        for (x, err) in bad {
+            assert_eq!(Asn1::Sequence(vec![x]).encode(), Err(err));
+        }
+    }
+
+    #[test]
+    fn der_is_strict_and_ber_is_not() {
+        let cases = [
+            // length in long form
+            ("308103020105", Asn1Error::NonMinimalLength),
+            // leading zero length bytes, the length trick used in berserk
+            ("308400000003020105", Asn1Error::NonMinimalLength),
+            // leading zero in an integer
+            ("300402020005", Asn1Error::NonMinimalInteger),
+            // indefinite length
+            ("30800201050000", Asn1Error::IndefiniteLength),
+            // nonzero padding bits
+            ("030204f1", Asn1Error::InvalidBitString),
+        ];
+        for (hex, err) in cases {
+            let bytes = from_hex(hex);
+            assert!(decode(&bytes, Mode::Ber).is_ok(), "{}", hex);
+            assert_eq!(decode(&bytes, Mode::Der), Err(err), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let mut ber = [SEQUENCE, 0x80].repeat(100000);
+        ber.extend([0; 200000]);
+        assert_eq!(decode(&ber, Mode::Ber), Err(Asn1Error::TooDeep));
+        let mut der = vec![];
+        for _ in 0..100 {
+            der = encode_tlv(SEQUENCE, &der);
+        }
+        assert_eq!(decode(&der, Mode::Der), Err(Asn1Error::TooDeep));
+        let der = (0..MAX_DEPTH).fold(vec![], |der, _| encode_tlv(SEQUENCE, &der));
+        assert!(decode(&der, Mode::Der).is_ok());
+    }
+
+    #[test]
+    fn garbage_does_not_panic() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10000 {
+            let len = rng.gen_range(0..20);
+            let mut bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            if rng.gen() && !bytes.is_empty() {
+                bytes[0] = [SEQUENCE, INTEGER, OID, BIT_STRING, 0xa0][rng.gen_range(0..5)];
+            }
+            let _ = decode(&bytes, Mode::Der);
+            let _ = decode(&bytes, Mode::Ber);
+        }
+    }
+}
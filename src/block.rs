@@ -1,3 +1,4 @@
+use crate::ct::{eq_mask, lt_mask};
 use crate::xor::xor_arr;
 use std::collections::HashSet;
 
@@ -61,27 +62,37 @@ pub fn rand_key<const N: usize, T: rand::Rng>(rng: &mut T) -> [u8; N] {
     rng.gen()
 }
 
-pub fn md_be_pad(arr: &[u8], len_bits: usize) -> Vec<u8> {
-    let ml = arr.len() * 8; // len in bits
-    let mut v = Vec::from(arr);
-    v.push(0x80);
-    let len = len_bits / 8;
+fn md_glue(len: usize, len_bits: usize) -> Vec<u8> {
+    let mut v = vec![0x80];
+    let len_bytes = len_bits / 8;
     // - 8 to save space for ml.to_be_bytes;
-    let diff = (2 * len - 8 - v.len() % len) % len;
+    let diff = (2 * len_bytes - 8 - (len + 1) % len_bytes) % len_bytes;
     v.extend(vec![0; diff]);
-    v.extend(ml.to_be_bytes());
+    v
+}
+
+// padding that follows a message of len bytes
+pub fn md_be_glue(len: usize, len_bits: usize) -> Vec<u8> {
+    let mut v = md_glue(len, len_bits);
+    v.extend((len as u64 * 8).to_be_bytes());
+    v
+}
+
+pub fn md_le_glue(len: usize, len_bits: usize) -> Vec<u8> {
+    let mut v = md_glue(len, len_bits);
+    v.extend((len as u64 * 8).to_le_bytes());
+    v
+}
+
+pub fn md_be_pad(arr: &[u8], len_bits: usize) -> Vec<u8> {
+    let mut v = Vec::from(arr);
+    v.extend(md_be_glue(arr.len(), len_bits));
     v
 }
 
 pub fn md_le_pad(arr: &[u8], len_bits: usize) -> Vec<u8> {
-    let ml = arr.len() * 8; // len in bits
     let mut v = Vec::from(arr);
-    v.push(0x80);
-    let len = len_bits / 8;
-    // - 8 to save space for ml.to_be_bytes;
-    let diff = (2 * len - 8 - v.len()) % len;
-    v.extend(vec![0; diff]);
-    v.extend(ml.to_le_bytes());
+    v.extend(md_le_glue(arr.len(), len_bits));
     v
 }
 
@@ -105,7 +116,9 @@ pub fn unpad_pkcs7(mut v: Vec<u8>) -> Option<Vec<u8>> {
     Some(v)
 }
 
-pub fn check_cbc_padding<const N: usize>(
+// unpads with an early exit, so besides the padding oracle itself the time it
+// takes tells where the padding went wrong
+pub fn check_cbc_padding_insecure<const N: usize>(
     arr: &[u8],
     key: &[u8; N],
     iv: &[u8; N],
@@ -114,6 +127,29 @@ pub fn check_cbc_padding<const N: usize>(
     cbc_decr(arr, key, iv, f).and_then(unpad_pkcs7).is_some()
 }
 
+// branches only on the (public) length and the block size n
+pub fn valid_pkcs7_ct(v: &[u8], n: u8) -> bool {
+    if v.len() < n as usize || n == 0 {
+        return false;
+    }
+    let pad = v[v.len() - 1];
+    let mut ok = !eq_mask(pad, 0) & !lt_mask(n, pad);
+    for (i, b) in v[v.len() - n as usize..].iter().rev().enumerate() {
+        let in_pad = lt_mask(i as u8, pad);
+        ok &= !in_pad | eq_mask(*b, pad);
+    }
+    ok == 0xff
+}
+
+pub fn check_cbc_padding<const N: usize>(
+    arr: &[u8],
+    key: &[u8; N],
+    iv: &[u8; N],
+    f: fn(&[u8; N], &[u8; N]) -> [u8; N],
+) -> bool {
+    cbc_decr(arr, key, iv, f).is_some_and(|v| valid_pkcs7_ct(&v, N as u8))
+}
+
 pub fn pad_with(prefix: &[u8], arr: &[u8], postfix: &[u8], bs: u8) -> Vec<u8> {
     let mut v = Vec::from(prefix);
     v.extend(arr);
@@ -350,6 +386,18 @@ mod tests {
         assert_eq!(unpad_pkcs7(input), Some(vec![]));
     }
 
+    #[test]
+    fn valid_pkcs7_ct_works() {
+        assert!(valid_pkcs7_ct(&[b'a', b'b', b'c', 1], 4));
+        assert!(valid_pkcs7_ct(&[b'a', b'k', 2, 2], 4));
+        assert!(valid_pkcs7_ct(&[4, 4, 4, 4], 4));
+        assert!(valid_pkcs7_ct(&[9, 9, 9, 9, 4, 4, 4, 4], 4));
+        assert!(!valid_pkcs7_ct(&[b'a', b'k', 2, 4], 4));
+        assert!(!valid_pkcs7_ct(&[b'a', b'k', 3, 0], 4));
+        assert!(!valid_pkcs7_ct(&[5, 5, 5, 5, 5, 5, 5, 5], 4));
+        assert!(!valid_pkcs7_ct(&[1, 1], 4));
+    }
+
     #[test]
     fn detect_blocksize_works() {
         let mut rng = rand::thread_rng();
@@ -467,10 +515,13 @@ mod tests {
             .try_into()
             .expect("incorrect block size");
 
-        let oracle = |v: &[u8; N], iv: &[u8; N]| check_cbc_padding(v, &key, iv, inv_aes);
-        let mut v = decrypt_cbc_block(&encr, &oracle).unwrap();
-        xor_arr(&mut v, &iv);
-        assert_eq!(&v, &input);
+        // a padding oracle either way, only the timing differs
+        for check in [check_cbc_padding, check_cbc_padding_insecure] {
+            let oracle = |v: &[u8; N], iv: &[u8; N]| check(v, &key, iv, inv_aes);
+            let mut v = decrypt_cbc_block(&encr, &oracle).unwrap();
+            xor_arr(&mut v, &iv);
+            assert_eq!(&v, &input);
+        }
     }
 
     #[test]
@@ -0,0 +1,194 @@
+use num_bigint::BigUint;
+use std::fmt;
+use std::hint::black_box;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+// masks are 0xff for true and 0x00 for false
+pub fn mask(c: bool) -> u8 {
+    black_box(c as u8).wrapping_neg()
+}
+
+pub fn eq_mask(a: u8, b: u8) -> u8 {
+    let x = (a ^ b) as u16;
+    // x - 1 underflows into the high byte only when x == 0
+    (x.wrapping_sub(1) >> 8) as u8
+}
+
+pub fn lt_mask(a: u8, b: u8) -> u8 {
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+pub fn select(m: u8, a: u8, b: u8) -> u8 {
+    b ^ (m & (a ^ b))
+}
+
+pub fn cswap(m: u8, a: &mut [u8], b: &mut [u8]) {
+    assert_eq!(a.len(), b.len(), "lengths must match");
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        let t = m & (*x ^ *y);
+        *x ^= t;
+        *y ^= t;
+    }
+}
+
+// the lengths are considered public
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0, |d, (x, y)| d | (x ^ y));
+    black_box(eq_mask(diff, 0)) == 0xff
+}
+
+// compares the numbers as len-byte big-endian strings
+pub fn eq_biguint(a: &BigUint, b: &BigUint, len: usize) -> bool {
+    let pad = |x: &BigUint| {
+        let bs = x.to_bytes_be();
+        let mut v = vec![0; len.saturating_sub(bs.len())];
+        v.extend(bs);
+        v
+    };
+    eq(&pad(a), &pad(b))
+}
+
+pub trait Zeroize {
+    fn zeroize(&mut self);
+
+    // the same value in memory that zeroize reaches all of, see Secret::new
+    fn packed(self) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+impl<const N: usize> Zeroize for [u8; N] {
+    fn zeroize(&mut self) {
+        for b in self.iter_mut() {
+            unsafe { ptr::write_volatile(b, 0) };
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl Zeroize for Vec<u8> {
+    // once cleared the whole allocation is spare capacity, so the bytes past the
+    // old length are wiped too
+    fn zeroize(&mut self) {
+        self.clear();
+        for b in self.spare_capacity_mut() {
+            unsafe { ptr::write_volatile(b, MaybeUninit::new(0)) };
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl Zeroize for BigUint {
+    // set_bit shrinks the digits, moving them to a new buffer, once the top one
+    // is cleared, so the bits go from the bottom up and are all zero by then
+    fn zeroize(&mut self) {
+        for i in 0..self.bits() {
+            self.set_bit(i, false);
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    // the digits past the length that arithmetic leaves behind can't be reached
+    // through num-bigint, but a clone has none
+    fn packed(mut self) -> Self {
+        let x = self.clone();
+        self.zeroize();
+        x
+    }
+}
+
+// zeroized when dropped and redacted when printed. the value is packed first, so
+// the drop wipes all the memory the secret holds. this is no guarantee that the
+// secret is gone from memory: num-bigint frees its intermediates and the spare
+// capacity of the value given to new without clearing them
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(x: T) -> Self {
+        Self(x.packed())
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn masks_work() {
+        assert_eq!(mask(true), 0xff);
+        assert_eq!(mask(false), 0);
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (255, 255), (17, 200), (200, 17)] {
+            assert_eq!(eq_mask(a, b), mask(a == b));
+            assert_eq!(lt_mask(a, b), mask(a < b));
+        }
+        assert_eq!(select(0xff, 3, 7), 3);
+        assert_eq!(select(0, 3, 7), 7);
+    }
+
+    #[test]
+    fn cswap_works() {
+        let mut a = [1, 2, 3];
+        let mut b = [4, 5, 6];
+        cswap(0, &mut a, &mut b);
+        assert_eq!((a, b), ([1, 2, 3], [4, 5, 6]));
+        cswap(0xff, &mut a, &mut b);
+        assert_eq!((a, b), ([4, 5, 6], [1, 2, 3]));
+    }
+
+    #[test]
+    fn eq_works() {
+        let mut rng = rand::thread_rng();
+        let a: [u8; 32] = rng.gen();
+        let mut b = a;
+        assert!(eq(&a, &b));
+        b[rng.gen_range(0..32)] ^= 1 << rng.gen_range(0..8);
+        assert!(!eq(&a, &b));
+        assert!(!eq(&a, &a[1..]));
+        assert!(eq_biguint(&5u8.into(), &5u8.into(), 20));
+        assert!(!eq_biguint(&5u8.into(), &6u8.into(), 20));
+    }
+
+    #[test]
+    fn zeroize_works() {
+        let mut x: BigUint = BigUint::from(u128::MAX) << 100u8;
+        x.zeroize();
+        assert_eq!(x, 0u8.into());
+        let mut v = vec![1, 2, 3];
+        v.zeroize();
+        assert!(v.is_empty());
+        let s = Secret::new([7u8; 4]);
+        assert_eq!(s.expose(), &[7; 4]);
+        assert_eq!(format!("{:?}", s), "Secret(..)");
+    }
+}
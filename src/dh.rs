@@ -1,6 +1,8 @@
-use crate::digest::sha1;
-use crate::math::{crt, disc_log_incr, div, pollard_lambda};
-use crate::prime::factors_up_to;
+use crate::digest::{sha1, Sha256};
+use crate::group::Group;
+use crate::kdf::hkdf;
+use crate::math::{crt, disc_log_incr, kangaroo};
+use crate::prime::{factors_up_to, provable_safe_prime, safe_prime, Certificate};
 use num_bigint::{BigUint, RandBigInt};
 
 pub fn dh_keys(p: &BigUint, g: &BigUint, rng: &mut impl rand::Rng) -> (BigUint, BigUint) {
@@ -9,6 +11,21 @@ pub fn dh_keys(p: &BigUint, g: &BigUint, rng: &mut impl rand::Rng) -> (BigUint,
     (pubkey, privkey)
 }
 
+// a safe prime p with g = 4, a square and so a generator of the subgroup of
+// prime order (p - 1) / 2, out of reach of the small subgroup attacks
+pub fn dh_safe_group(bits: u64, rng: &mut impl rand::Rng) -> (BigUint, BigUint) {
+    (safe_prime(bits, 20, rng), 4u8.into())
+}
+
+// dh_safe_group with a certificate that p is prime, for those who audit it
+pub fn dh_proven_safe_group(
+    bits: u64,
+    rng: &mut impl rand::Rng,
+) -> (BigUint, BigUint, Certificate) {
+    let (p, cert) = provable_safe_prime(bits, rng);
+    (p, 4u8.into(), cert)
+}
+
 pub fn dh_session_key(p: &BigUint, pb0: &BigUint, priv1: &BigUint) -> [u8; 16] {
     let s = pb0.modpow(priv1, p);
     let mut res = [0u8; 16];
@@ -16,66 +33,85 @@ pub fn dh_session_key(p: &BigUint, pb0: &BigUint, priv1: &BigUint) -> [u8; 16] {
     res
 }
 
-pub fn subgroups(
+// derives the key with hkdf-sha256 instead of truncating sha1 of the shared secret
+pub fn dh_session_key_hkdf(
     p: &BigUint,
+    pb0: &BigUint,
+    priv1: &BigUint,
+    salt: &[u8],
+    info: &[u8],
+) -> [u8; 16] {
+    let s = pb0.modpow(priv1, p);
+    let okm = hkdf::<Sha256, 32>(salt, &s.to_bytes_be(), info, 16);
+    okm.try_into().expect("invalid key length")
+}
+
+// elements of small prime order f for each f < 2^b that divides the order of the
+// group over q, or of order f^e when those together stay below q
+pub fn subgroups<G: Group>(
+    group: &G,
     q: &BigUint,
     b: u32,
     rng: &mut impl rand::Rng,
-) -> Vec<(usize, BigUint)> {
-    let pm1 = &(p - 1u8);
-    let j = pm1 / q;
+) -> Vec<(usize, G::Element)> {
+    let n = &group.order();
+    let j = n / q;
     let factors = factors_up_to(&j, 2usize.pow(b));
     let mut orders = vec![];
-    let one = BigUint::from(1u8);
+    let one = group.identity();
     let fp: &BigUint = &factors
         .iter()
         .map(|(f, e)| BigUint::from(*f).pow(*e))
         .product();
     for (f, e) in factors {
-        let mut f = f;
-        if fp < q {
-            f = f.pow(e);
-        }
-        assert!(pm1 % f == 0u8.into());
-        let mut h = rng.gen_biguint_range(&1u8.into(), p).modpow(&(pm1 / f), p);
-        while h == one {
-            h = rng.gen_biguint_range(&1u8.into(), p).modpow(&(pm1 / f), p);
-        }
-        orders.push((f, h))
+        let fe = f.pow(e);
+        assert!(n % fe == 0u8.into());
+        let h = loop {
+            let mut h = group.exp(&group.random(rng), &(n / fe));
+            // when the f-part is not cyclic (two points of order 2 on a curve, say)
+            // g^(n / f) is always 1, so go down from order f^e to order f instead
+            while fp >= q && group.exp(&h, &f.into()) != one {
+                h = group.exp(&h, &f.into());
+            }
+            if h != one {
+                break h;
+            }
+        };
+        orders.push((if fp < q { fe } else { f }, h))
     }
     orders
 }
 
-pub fn get_privkey_mod_from_crt(
-    subgroups: &[(usize, BigUint)],
-    p: &BigUint,
-    sign: impl Fn(&BigUint) -> BigUint,
+pub fn get_privkey_mod_from_crt<G: Group>(
+    group: &G,
+    subgroups: &[(usize, G::Element)],
+    sign: impl Fn(&G::Element) -> G::Element,
 ) -> Option<(BigUint, BigUint)> {
     subgroups
-        .into_iter()
+        .iter()
         .map(|(f, h)| {
             let f = BigUint::from(*f);
-            // log(h ^ x mod p) = x % r (when h is of order r)
-            let lhx = disc_log_incr(p, &f, h, &sign(h))?;
+            // log(h ^ x) = x % r (when h is of order r)
+            let lhx = disc_log_incr(group, &f, h, &sign(h))?;
             Some((lhx, f))
         })
         .collect::<Option<Vec<_>>>()
         .map(|v| crt(&v))
 }
 
-pub fn get_privkey_from_rem(
-    p: &BigUint,
+pub fn get_privkey_from_rem<G: Group + Sync>(
+    group: &G,
     q: &BigUint,
-    g: &BigUint,
-    y: &BigUint,
+    g: &G::Element,
+    y: &G::Element,
     n: &BigUint,
     r: &BigUint,
 ) -> Option<BigUint> {
-    let g_ = &g.modpow(r, p);
-    let y_ = &div(y, &g.modpow(n, p), p)?;
+    let g_ = &group.exp(g, r);
+    let y_ = &group.op(y, &group.inverse(&group.exp(g, n)));
     let ul = (q - 1u8) / r;
-    let k = ul.bits() as u8 / 2;
-    let m = pollard_lambda(p, g_, y_, &0u8.into(), &ul, k, 4)?;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let m = kangaroo(group, g_, y_, &0u8.into(), &ul, threads, |_, _| {})?;
     Some(n + m * r)
 }
 
@@ -83,7 +119,8 @@ pub fn get_privkey_from_rem(
 mod tests {
     use super::*;
     use crate::encode::from_hex;
-    use crate::prime::{mr_prime, pq};
+    use crate::group::ModGroup;
+    use crate::prime::{mr_prime, pq, verify_certificate};
 
     #[test]
     fn gen_key_works() {
@@ -97,6 +134,45 @@ mod tests {
         assert_eq!(s0, s1)
     }
 
+    #[test]
+    fn hkdf_key_works() {
+        let mut rng = rand::thread_rng();
+        let p = mr_prime(256, 5, &mut rng);
+        let g = 2u8.into();
+        let (pb0, priv0) = dh_keys(&p, &g, &mut rng);
+        let (pb1, priv1) = dh_keys(&p, &g, &mut rng);
+        let s0 = dh_session_key_hkdf(&p, &pb1, &priv0, b"salt", b"handshake");
+        let s1 = dh_session_key_hkdf(&p, &pb0, &priv1, b"salt", b"handshake");
+        assert_eq!(s0, s1);
+        assert_ne!(s0, dh_session_key(&p, &pb1, &priv0));
+        let s2 = dh_session_key_hkdf(&p, &pb0, &priv1, b"salt", b"other");
+        assert_ne!(s0, s2);
+    }
+
+    #[test]
+    fn dh_safe_group_works() {
+        let mut rng = rand::thread_rng();
+        let (p, g) = dh_safe_group(256, &mut rng);
+        let q = &p >> 1;
+        assert_eq!(g.modpow(&q, &p), 1u8.into());
+        let (pb0, priv0) = dh_keys(&p, &g, &mut rng);
+        let (pb1, priv1) = dh_keys(&p, &g, &mut rng);
+        assert_eq!(pb0.modpow(&q, &p), 1u8.into());
+        assert_eq!(
+            dh_session_key(&p, &pb1, &priv0),
+            dh_session_key(&p, &pb0, &priv1)
+        );
+    }
+
+    #[test]
+    fn dh_proven_safe_group_works() {
+        let mut rng = rand::thread_rng();
+        let (p, g, cert) = dh_proven_safe_group(256, &mut rng);
+        assert!(verify_certificate(&cert));
+        assert_eq!(cert.prime(), p);
+        assert_eq!(g.modpow(&(&p >> 1), &p), 1u8.into());
+    }
+
     #[test]
     fn gen_key_works_with_bigger_numbers() {
         let mut rng = rand::thread_rng();
@@ -161,11 +237,15 @@ fffffffffffff"
         let p = &BigUint::parse_bytes(b"7199773997391911030609999317773941274322764333428698921736339643928346453700085358802973900485592910475480089726140708102474957429903531369589969318716771", 10).expect("not a number");
         let q = &BigUint::parse_bytes(b"236234353446506858198510045061214171961", 10)
             .expect("not a number");
-        let subs = subgroups(&p, &q, 16, &mut rng);
+        let group = ModGroup {
+            p: p.clone(),
+            order: p - 1u8,
+        };
+        let subs = subgroups(&group, &q, 16, &mut rng);
 
         let privkey = rng.gen_biguint_range(&2u8.into(), q);
         let sign = |msg: &BigUint| msg.modpow(&privkey, &p);
-        let (pk, _) = get_privkey_mod_from_crt(&subs, p, sign).expect("pk not found");
+        let (pk, _) = get_privkey_mod_from_crt(&group, &subs, sign).expect("pk not found");
         assert_eq!(pk, privkey);
     }
 
@@ -180,7 +260,11 @@ fffffffffffff"
         assert_ne!(privkey, n); // not completely revealed by mod r
         let g = &rng.gen_biguint_range(&2u8.into(), &(p - 2u8));
         let y = &g.modpow(&privkey, &p);
-        let pk = get_privkey_from_rem(p, q, g, y, &n, r).expect("pk not found");
+        let group = ModGroup {
+            p: p.clone(),
+            order: p - 1u8,
+        };
+        let pk = get_privkey_from_rem(&group, q, g, y, &n, r).expect("pk not found");
         assert_eq!(privkey, pk);
     }
 }
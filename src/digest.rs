@@ -1,5 +1,6 @@
-use crate::block::{md_be_pad, md_le_pad};
-use crate::xor::xor_arr;
+use crate::block::{md_be_glue, md_le_glue};
+use crate::xor::xor_slice;
+use std::io::{self, Read};
 use std::num::Wrapping;
 
 type W32 = Wrapping<u32>;
@@ -12,6 +13,75 @@ fn rr(w: W32, n: usize) -> W32 {
     w >> n | w << 32 - n % 32
 }
 
+pub trait Hasher<const N: usize>: Sized {
+    const BLOCK_SIZE: usize = 64;
+
+    // the algorithm identifier used in signatures
+    const OID: &'static [u64];
+
+    fn new() -> Self;
+
+    // continue from a digest of len bytes of (padded) input, len must be a multiple of BLOCK_SIZE
+    fn from_state(digest: &[u8; N], len: u64) -> Self;
+
+    // the chaining value and the number of bytes it covers, buffered input is not included
+    fn state(&self) -> ([u8; N], u64);
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> [u8; N];
+
+    // the padding that follows a message of len bytes
+    fn glue(len: usize) -> Vec<u8>;
+
+    fn digest(msg: &[u8]) -> [u8; N] {
+        let mut h = Self::new();
+        h.update(msg);
+        h.finalize()
+    }
+
+    fn update_reader(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut buf = [0u8; 1 << 16];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.update(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn digest_reader(reader: impl Read) -> io::Result<[u8; N]> {
+        let mut h = Self::new();
+        h.update_reader(reader)?;
+        Ok(h.finalize())
+    }
+}
+
+// appends data to buf and splits off the full blocks
+fn take_blocks(buf: &mut Vec<u8>, data: &[u8]) -> Vec<u8> {
+    buf.extend(data);
+    let rest = buf.split_off(buf.len() - buf.len() % 64);
+    std::mem::replace(buf, rest)
+}
+
+fn words_to_bytes<const N: usize>(words: impl Iterator<Item = [u8; 4]>) -> [u8; N] {
+    let mut res = [0; N];
+    for (i, v) in words.flatten().enumerate() {
+        res[i] = v;
+    }
+    res
+}
+
+fn bytes_to_words<const S: usize>(bytes: &[u8], f: fn([u8; 4]) -> u32) -> [W32; S] {
+    let mut res = [Wrapping(0); S];
+    for (i, chk) in bytes.chunks_exact(4).enumerate() {
+        res[i] = Wrapping(f(chk.try_into().expect("invalid bs")));
+    }
+    res
+}
+
 fn md4r1(arr: &[u32; 4], xi: u32, s: u32) -> u32 {
     let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
     arr[0]
@@ -37,13 +107,8 @@ fn md4r3(a: &[u32; 4], xi: u32, s: u32) -> u32 {
         .rotate_left(s)
 }
 
-pub fn md4(msg: &[u8]) -> [u8; 16] {
-    let v = md_le_pad(msg, 512);
-
-    let mut a = 0x67452301;
-    let mut b = 0xefcdab89;
-    let mut c = 0x98badcfe;
-    let mut d = 0x10325476;
+fn md4_loop(v: &[u8], h: [W32; 4]) -> [W32; 4] {
+    let [mut a, mut b, mut c, mut d] = h.map(|x| x.0);
 
     for chunk in v.chunks_exact(64) {
         let mut x = [0u32; 16];
@@ -75,13 +140,63 @@ pub fn md4(msg: &[u8]) -> [u8; 16] {
         c = c.wrapping_add(abcd[2]);
         d = d.wrapping_add(abcd[3]);
     }
-    let bytes = [a, b, c, d].into_iter().map(|h| h.to_le_bytes());
+    [a, b, c, d].map(Wrapping)
+}
 
-    let mut res = [0u8; 16];
-    for (i, v) in bytes.flatten().enumerate() {
-        res[i] = v;
+pub struct Md4 {
+    h: [W32; 4],
+    buf: Vec<u8>,
+    len: u64,
+}
+
+impl Hasher<16> for Md4 {
+    const OID: &'static [u64] = &[1, 2, 840, 113549, 2, 4];
+
+    fn new() -> Self {
+        let h = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476].map(Wrapping);
+        Self::from_words(h, 0)
+    }
+
+    fn from_state(digest: &[u8; 16], len: u64) -> Self {
+        Self::from_words(bytes_to_words(digest, u32::from_le_bytes), len)
+    }
+
+    fn state(&self) -> ([u8; 16], u64) {
+        let bytes = self.h.into_iter().map(|h| h.0.to_le_bytes());
+        (words_to_bytes(bytes), self.len)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let blocks = take_blocks(&mut self.buf, data);
+        self.len += blocks.len() as u64;
+        self.h = md4_loop(&blocks, self.h);
+    }
+
+    fn finalize(mut self) -> [u8; 16] {
+        let len = self.len as usize + self.buf.len();
+        self.buf.extend(Self::glue(len));
+        let h = md4_loop(&self.buf, self.h);
+        words_to_bytes(h.into_iter().map(|h| h.0.to_le_bytes()))
+    }
+
+    fn glue(len: usize) -> Vec<u8> {
+        md_le_glue(len, 512)
     }
-    res
+}
+
+impl Md4 {
+    fn from_words(h: [W32; 4], len: u64) -> Self {
+        assert_eq!(len % 64, 0, "len must be a multiple of the block size");
+        Self {
+            h,
+            buf: vec![],
+            len,
+        }
+    }
+}
+
+pub fn md4(msg: &[u8]) -> [u8; 16] {
+    Md4::digest(msg)
 }
 
 fn sha1_round(w: &[W32; 80], h: &[W32; 5], k: &[W32; 4]) -> [W32; 5] {
@@ -117,20 +232,64 @@ fn sha1_loop(v: &[u8], mut h: [W32; 5]) -> [W32; 5] {
     h
 }
 
-pub fn sha1(msg: &[u8]) -> [u8; 20] {
-    let v = md_be_pad(msg, 512);
-    let h0 = 0x67452301_u32;
-    let h1 = 0xEFCDAB89_u32;
-    let h2 = 0x98BADCFE_u32;
-    let h3 = 0x10325476_u32;
-    let h4 = 0xC3D2E1F0_u32;
-    let h = sha1_loop(&v, [h0, h1, h2, h3, h4].map(Wrapping)).map(|x| x.0);
-
-    let mut res = [0; 20];
-    for (i, v) in h.into_iter().map(|h| h.to_be_bytes()).flatten().enumerate() {
-        res[i] = v;
+pub struct Sha1 {
+    h: [W32; 5],
+    buf: Vec<u8>,
+    len: u64,
+}
+
+impl Hasher<20> for Sha1 {
+    const OID: &'static [u64] = &[1, 3, 14, 3, 2, 26];
+
+    fn new() -> Self {
+        let h0 = 0x67452301_u32;
+        let h1 = 0xEFCDAB89_u32;
+        let h2 = 0x98BADCFE_u32;
+        let h3 = 0x10325476_u32;
+        let h4 = 0xC3D2E1F0_u32;
+        Self::from_words([h0, h1, h2, h3, h4].map(Wrapping), 0)
     }
-    res
+
+    fn from_state(digest: &[u8; 20], len: u64) -> Self {
+        Self::from_words(bytes_to_words(digest, u32::from_be_bytes), len)
+    }
+
+    fn state(&self) -> ([u8; 20], u64) {
+        let bytes = self.h.into_iter().map(|h| h.0.to_be_bytes());
+        (words_to_bytes(bytes), self.len)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let blocks = take_blocks(&mut self.buf, data);
+        self.len += blocks.len() as u64;
+        self.h = sha1_loop(&blocks, self.h);
+    }
+
+    fn finalize(mut self) -> [u8; 20] {
+        let len = self.len as usize + self.buf.len();
+        self.buf.extend(Self::glue(len));
+        let h = sha1_loop(&self.buf, self.h);
+        words_to_bytes(h.into_iter().map(|h| h.0.to_be_bytes()))
+    }
+
+    fn glue(len: usize) -> Vec<u8> {
+        md_be_glue(len, 512)
+    }
+}
+
+impl Sha1 {
+    fn from_words(h: [W32; 5], len: u64) -> Self {
+        assert_eq!(len % 64, 0, "len must be a multiple of the block size");
+        Self {
+            h,
+            buf: vec![],
+            len,
+        }
+    }
+}
+
+pub fn sha1(msg: &[u8]) -> [u8; 20] {
+    Sha1::digest(msg)
 }
 
 fn sha256_round(w: &[W32; 64], h: &[W32; 8], k: &[W32; 64]) -> [W32; 8] {
@@ -180,49 +339,127 @@ fn sha256_loop(v: &[u8], mut h: [W32; 8]) -> [W32; 8] {
     h
 }
 
-pub fn sha256(msg: &[u8]) -> [u8; 32] {
-    let v = md_be_pad(msg, 512);
-    let h0 = 0x6a09e667;
-    let h1 = 0xbb67ae85;
-    let h2 = 0x3c6ef372;
-    let h3 = 0xa54ff53a;
-    let h4 = 0x510e527f;
-    let h5 = 0x9b05688c;
-    let h6 = 0x1f83d9ab;
-    let h7 = 0x5be0cd19;
-    let h = [h0, h1, h2, h3, h4, h5, h6, h7].map(Wrapping);
-    let h = sha256_loop(&v, h).map(|h| h.0);
-    let mut res = [0; 32];
-
-    for (i, v) in h.into_iter().map(|h| h.to_be_bytes()).flatten().enumerate() {
-        res[i] = v;
-    }
-    res
+pub struct Sha256 {
+    h: [W32; 8],
+    buf: Vec<u8>,
+    len: u64,
 }
 
-pub fn hmac_key<const N: usize>(key: &[u8], h: fn(&[u8]) -> [u8; N]) -> [u8; N] {
-    if key.len() > N {
-        return h(key);
+impl Hasher<32> for Sha256 {
+    const OID: &'static [u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+
+    fn new() -> Self {
+        let h0 = 0x6a09e667;
+        let h1 = 0xbb67ae85;
+        let h2 = 0x3c6ef372;
+        let h3 = 0xa54ff53a;
+        let h4 = 0x510e527f;
+        let h5 = 0x9b05688c;
+        let h6 = 0x1f83d9ab;
+        let h7 = 0x5be0cd19;
+        Self::from_words([h0, h1, h2, h3, h4, h5, h6, h7].map(Wrapping), 0)
+    }
+
+    fn from_state(digest: &[u8; 32], len: u64) -> Self {
+        Self::from_words(bytes_to_words(digest, u32::from_be_bytes), len)
+    }
+
+    fn state(&self) -> ([u8; 32], u64) {
+        let bytes = self.h.into_iter().map(|h| h.0.to_be_bytes());
+        (words_to_bytes(bytes), self.len)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let blocks = take_blocks(&mut self.buf, data);
+        self.len += blocks.len() as u64;
+        self.h = sha256_loop(&blocks, self.h);
     }
-    let mut res = [0u8; N];
-    for i in 0..key.len() {
-        res[i] = key[i];
+
+    fn finalize(mut self) -> [u8; 32] {
+        let len = self.len as usize + self.buf.len();
+        self.buf.extend(Self::glue(len));
+        let h = sha256_loop(&self.buf, self.h);
+        words_to_bytes(h.into_iter().map(|h| h.0.to_be_bytes()))
+    }
+
+    fn glue(len: usize) -> Vec<u8> {
+        md_be_glue(len, 512)
+    }
+}
+
+impl Sha256 {
+    fn from_words(h: [W32; 8], len: u64) -> Self {
+        assert_eq!(len % 64, 0, "len must be a multiple of the block size");
+        Self {
+            h,
+            buf: vec![],
+            len,
+        }
     }
+}
+
+pub fn sha256(msg: &[u8]) -> [u8; 32] {
+    Sha256::digest(msg)
+}
+
+// the key padded (or hashed) to the block size
+pub fn hmac_key<H: Hasher<N>, const N: usize>(key: &[u8]) -> Vec<u8> {
+    let mut res = match key.len() > H::BLOCK_SIZE {
+        true => Vec::from(H::digest(key)),
+        false => Vec::from(key),
+    };
+    res.resize(H::BLOCK_SIZE, 0);
     res
 }
 
-pub fn hmac<const N: usize>(key: &[u8], msg: &[u8], h: fn(&[u8]) -> [u8; N]) -> [u8; N] {
-    let mut key = hmac_key(key, h);
-    xor_arr(&mut key, &[0x36; N]);
-    let h0 = h(&[&key, msg].concat());
-    xor_arr(&mut key, &[0x36 ^ 0x5c; N]);
-    h(&[key, h0].concat())
+pub fn hmac<H: Hasher<N>, const N: usize>(key: &[u8], msg: &[u8]) -> [u8; N] {
+    let mut key = hmac_key::<H, N>(key);
+    xor_slice(&mut key, &[0x36]);
+    let mut inner = H::new();
+    inner.update(&key);
+    inner.update(msg);
+    let h0 = inner.finalize();
+    xor_slice(&mut key, &[0x36 ^ 0x5c]);
+    let mut outer = H::new();
+    outer.update(&key);
+    outer.update(&h0);
+    outer.finalize()
+}
+
+// forges H(secret || msg || glue || ext) from digest = H(secret || msg)
+pub fn extend_length<H: Hasher<N>, const N: usize>(
+    digest: &[u8; N],
+    msg: &[u8],
+    secret_len: usize,
+    ext: &[u8],
+) -> (Vec<u8>, [u8; N]) {
+    let glue = H::glue(secret_len + msg.len());
+    let len = secret_len + msg.len() + glue.len();
+    let mut h = H::from_state(digest, len as u64);
+    h.update(ext);
+    let mut forged = Vec::from(msg);
+    forged.extend(glue);
+    forged.extend(ext);
+    (forged, h.finalize())
+}
+
+pub fn length_extension<H: Hasher<N>, const N: usize>(
+    digest: &[u8; N],
+    msg: &[u8],
+    ext: &[u8],
+    max_secret_len: usize,
+    verify: impl Fn(&[u8], &[u8; N]) -> bool,
+) -> Option<(Vec<u8>, [u8; N])> {
+    (0..=max_secret_len)
+        .map(|l| extend_length::<H, N>(digest, msg, l, ext))
+        .find(|(forged, d)| verify(forged, d))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::encode::{from_ascii, to_hex};
+    use crate::encode::{from_ascii, to_ascii, to_hex};
+    use rand::Rng;
 
     #[test]
     fn md4_works() {
@@ -257,4 +494,92 @@ mod tests {
         let exp = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
         assert_eq!(to_hex(&v), exp);
     }
+
+    #[test]
+    fn update_works() {
+        let msg: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        for split in [0, 1, 55, 64, 65, 128, 999] {
+            let mut h = Sha1::new();
+            h.update(&msg[..split]);
+            h.update(&msg[split..]);
+            assert_eq!(h.finalize(), sha1(&msg));
+
+            let mut h = Sha256::new();
+            h.update(&msg[..split]);
+            h.update(&msg[split..]);
+            assert_eq!(h.finalize(), sha256(&msg));
+
+            let mut h = Md4::new();
+            h.update(&msg[..split]);
+            h.update(&msg[split..]);
+            assert_eq!(h.finalize(), md4(&msg));
+        }
+        assert_eq!(
+            Sha256::digest_reader(&msg[..]).expect("read failed"),
+            sha256(&msg)
+        );
+    }
+
+    #[test]
+    fn from_state_works() {
+        let msg: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let mut h = Sha256::new();
+        h.update(&msg[..130]);
+        let (digest, len) = h.state();
+        assert_eq!(len, 128);
+
+        let mut h = Sha256::from_state(&digest, len);
+        h.update(&msg[128..]);
+        assert_eq!(h.finalize(), sha256(&msg));
+
+        let mut h = Md4::new();
+        h.update(&msg[..64]);
+        let (digest, len) = h.state();
+        let mut h = Md4::from_state(&digest, len);
+        h.update(&msg[64..]);
+        assert_eq!(h.finalize(), md4(&msg));
+    }
+
+    #[test]
+    fn hmac_works() {
+        // rfc 2202 and rfc 4231
+        let key = [0x0b; 20];
+        let msg = from_ascii("Hi There");
+        let exp = "b617318655057264e28bc0b6fb378c8ef146be00";
+        assert_eq!(to_hex(&hmac::<Sha1, 20>(&key, &msg)), exp);
+        let exp = "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7";
+        assert_eq!(to_hex(&hmac::<Sha256, 32>(&key, &msg)), exp);
+
+        let key = [0xaa; 131];
+        let msg = from_ascii("Test Using Larger Than Block-Size Key - Hash Key First");
+        let exp = "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54";
+        assert_eq!(to_hex(&hmac::<Sha256, 32>(&key, &msg)), exp);
+    }
+
+    #[test]
+    fn length_extension_works() {
+        let mut rng = rand::thread_rng();
+        let key: Vec<u8> = (0..rng.gen_range(1..40)).map(|_| rng.gen()).collect();
+        let msg = from_ascii(
+            "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon",
+        );
+        let ext = from_ascii(";admin=true");
+
+        let mac = |msg: &[u8]| sha1(&[&key, msg].concat());
+        let verify = |msg: &[u8], d: &[u8; 20]| &mac(msg) == d;
+        let (forged, d) = length_extension::<Sha1, 20>(&mac(&msg), &msg, &ext, 64, verify)
+            .expect("secret length not found");
+        assert!(to_ascii(&forged).ends_with(";admin=true"));
+        assert_eq!(mac(&forged), d);
+
+        let mac = |msg: &[u8]| md4(&[&key, msg].concat());
+        let verify = |msg: &[u8], d: &[u8; 16]| &mac(msg) == d;
+        let (forged, d) = length_extension::<Md4, 16>(&mac(&msg), &msg, &ext, 64, verify)
+            .expect("secret length not found");
+        assert_eq!(mac(&forged), d);
+
+        let mac = |msg: &[u8]| sha256(&[&key, msg].concat());
+        let (forged, d) = extend_length::<Sha256, 32>(&mac(&msg), &msg, key.len(), &ext);
+        assert_eq!(mac(&forged), d);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::xor::xor_slice;
+use std::time::Duration;
 
 fn char_score(c: u8) -> usize {
     if c.is_ascii_whitespace() {
@@ -52,6 +53,23 @@ pub fn hamming_score(b1: &[u8], b2: &[u8]) -> isize {
     -(v.iter().fold(0, |s, b| s + b.count_ones()) as isize)
 }
 
+pub fn median(v: &mut [Duration]) -> Duration {
+    assert!(!v.is_empty(), "no samples to take the median of");
+    v.sort();
+    v[(v.len() - 1) / 2]
+}
+
+// mean without the trim-fraction of smallest and largest values, which leaves
+// at least one value for a trim below a half
+pub fn trimmed_mean(v: &mut [Duration], trim: f64) -> Duration {
+    assert!(!v.is_empty(), "no samples to take the mean of");
+    assert!((0.0..0.5).contains(&trim), "trim must be in [0, 0.5)");
+    v.sort();
+    let k = (v.len() as f64 * trim) as usize;
+    let kept = &v[k..v.len() - k];
+    kept.iter().sum::<Duration>() / kept.len() as u32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,4 +88,15 @@ mod tests {
         let result1 = hamming_score(&b1, &b2);
         assert_eq!(result1, -37);
     }
+
+    #[test]
+    fn median_works() {
+        let mut v = [5, 1, 4, 2, 3].map(Duration::from_millis);
+        assert_eq!(median(&mut v), Duration::from_millis(3));
+        let mut v = [100, 1, 2, 3, 2].map(Duration::from_millis);
+        assert_eq!(trimmed_mean(&mut v, 0.2), Duration::from_millis(7) / 3);
+        let mut v = [4, 1].map(Duration::from_millis);
+        assert_eq!(trimmed_mean(&mut v, 0.49), Duration::from_micros(2500));
+        assert_eq!(median(&mut v[..1]), Duration::from_millis(1));
+    }
 }
@@ -1,5 +1,8 @@
+use crate::ct::{eq_biguint, Secret};
+use crate::digest::Hasher;
 use crate::math::{div, inv_egcd};
-use crate::prime::pq;
+use crate::modular::ModContext;
+use crate::prime::{baillie_psw, mrp_check, pq, verify_certificate, Certificate};
 use num_bigint::{BigUint, RandBigInt};
 
 pub fn dsa_parameters(n: u64, l: u64, rng: &mut impl rand::Rng) -> (BigUint, BigUint, BigUint) {
@@ -13,19 +16,232 @@ pub fn dsa_parameters(n: u64, l: u64, rng: &mut impl rand::Rng) -> (BigUint, Big
     (p, q, g)
 }
 
+// the domain parameter seed and the counter of the search for p, which let anyone
+// redo the generation of fips 186-4 a.1.1.2
+#[derive(Clone, Debug, PartialEq)]
+pub struct DomainSeed {
+    pub seed: Vec<u8>,
+    pub counter: usize,
+}
+
+// the hash of x mod 2^(8 len), as len big-endian bytes
+fn hash_int<H: Hasher<N>, const N: usize>(x: &BigUint, len: usize) -> BigUint {
+    let bytes = x.to_bytes_be();
+    let mut padded = vec![0; len.saturating_sub(bytes.len())];
+    padded.extend(&bytes[bytes.len().saturating_sub(len)..]);
+    BigUint::from_bytes_be(&H::digest(&padded))
+}
+
+// q from the seed then the first prime p from the hashes of the following
+// integers, none if q is not a prime or no p turns up within 4 l tries
+fn pq_from_seed<H: Hasher<N>, const N: usize>(
+    l: u64,
+    n: u64,
+    seed: &[u8],
+    rng: &mut impl rand::Rng,
+) -> Option<(BigUint, BigUint, usize)> {
+    let outlen = 8 * N as u64;
+    let seed_int = BigUint::from_bytes_be(seed);
+    let u = hash_int::<H, N>(&seed_int, seed.len()) % (BigUint::from(1u8) << (n - 1));
+    let q = (BigUint::from(1u8) << (n - 1)) + &u + 1u8 - (&u % 2u8);
+    if !mrp_check(&q, 40, rng) {
+        return None;
+    }
+    let blocks = l.div_ceil(outlen) - 1;
+    let b = l - 1 - blocks * outlen;
+    let mut offset = seed_int + 1u8;
+    for counter in 0..4 * l as usize {
+        let mut w = BigUint::from(0u8);
+        for j in 0..=blocks {
+            let mut v = hash_int::<H, N>(&(&offset + j), seed.len());
+            if j == blocks {
+                v %= BigUint::from(1u8) << b;
+            }
+            w += v << (j * outlen);
+        }
+        // the x of l bits rounded down to 1 mod 2q
+        let x: BigUint = w + (BigUint::from(1u8) << (l - 1));
+        let c = &x % (&q << 1);
+        let p: BigUint = x - c + 1u8;
+        if p.bits() == l && mrp_check(&p, 40, rng) {
+            return Some((p, q, counter));
+        }
+        offset += blocks + 1;
+    }
+    None
+}
+
+// p of l bits and q of n bits as in fips 186-4 a.1.1.2, along with the seed
+// that proves they were not picked with a backdoor in mind
+pub fn fips_pq<H: Hasher<N>, const N: usize>(
+    l: u64,
+    n: u64,
+    rng: &mut impl rand::Rng,
+) -> (BigUint, BigUint, DomainSeed) {
+    assert!(8 * N as u64 >= n, "the hash must have at least n bits");
+    let mut seed = vec![0; n.div_ceil(8) as usize];
+    loop {
+        rng.fill(&mut seed[..]);
+        if let Some((p, q, counter)) = pq_from_seed::<H, N>(l, n, &seed, rng) {
+            return (p, q, DomainSeed { seed, counter });
+        }
+    }
+}
+
+// fips 186-4 a.1.1.3, p and q are the ones the seed leads to
+pub fn validate_fips_pq<H: Hasher<N>, const N: usize>(
+    p: &BigUint,
+    q: &BigUint,
+    seed: &DomainSeed,
+    rng: &mut impl rand::Rng,
+) -> bool {
+    let found = pq_from_seed::<H, N>(p.bits(), q.bits(), &seed.seed, rng);
+    found == Some((p.clone(), q.clone(), seed.counter))
+}
+
+// the canonical g of fips 186-4 a.2.3, rebuilt by anyone from the seed and index
+pub fn fips_generator<H: Hasher<N>, const N: usize>(
+    p: &BigUint,
+    q: &BigUint,
+    seed: &[u8],
+    index: u8,
+) -> Option<BigUint> {
+    let e = (p - 1u8) / q;
+    for count in 1..=u16::MAX {
+        let mut u = seed.to_vec();
+        u.extend(b"ggen");
+        u.push(index);
+        u.extend(count.to_be_bytes());
+        let g = BigUint::from_bytes_be(&H::digest(&u)).modpow(&e, p);
+        if g >= 2u8.into() {
+            return Some(g);
+        }
+    }
+    None
+}
+
+// fips 186-4 a.2.4, g is the canonical generator for the seed and index
+pub fn validate_fips_generator<H: Hasher<N>, const N: usize>(
+    p: &BigUint,
+    q: &BigUint,
+    g: &BigUint,
+    seed: &[u8],
+    index: u8,
+    rng: &mut impl rand::Rng,
+) -> bool {
+    validate_dsa_parameters(p, q, g, rng)
+        && fips_generator::<H, N>(p, q, seed, index).as_ref() == Some(g)
+}
+
+// the checks any (p, q, g) has to pass: p and q are primes, q divides p - 1
+// and g generates the subgroup of order q. the primes face baillie-psw as well
+// as random bases, since parameters from elsewhere may be built to fool the latter
+pub fn validate_dsa_parameters(
+    p: &BigUint,
+    q: &BigUint,
+    g: &BigUint,
+    rng: &mut impl rand::Rng,
+) -> bool {
+    let one = BigUint::from(1u8);
+    baillie_psw(p)
+        && baillie_psw(q)
+        && mrp_check(p, 40, rng)
+        && mrp_check(q, 40, rng)
+        && (p - 1u8) % q == 0u8.into()
+        && g > &one
+        && g < p
+        && g.modpow(q, p) == one
+}
+
+// validate_dsa_parameters with certificates in place of the probabilistic
+// tests, so p and q are proven primes
+pub fn validate_dsa_certificates(
+    p: &BigUint,
+    q: &BigUint,
+    g: &BigUint,
+    p_cert: &Certificate,
+    q_cert: &Certificate,
+) -> bool {
+    let one = BigUint::from(1u8);
+    p_cert.prime() == *p
+        && q_cert.prime() == *q
+        && verify_certificate(p_cert)
+        && verify_certificate(q_cert)
+        && (p - 1u8) % q == 0u8.into()
+        && g > &one
+        && g < p
+        && g.modpow(q, p) == one
+}
+
 pub fn dsa_keys<R: rand::Rng>(
     p: &BigUint,
     q: &BigUint,
     g: &BigUint,
     rng: &mut R,
-) -> (BigUint, BigUint) {
+) -> (Secret<BigUint>, BigUint) {
     let x = rng.gen_biguint_range(&1u8.into(), &(q - 1u8));
-    let y = g.modpow(&x, &p);
-    (x, y)
+    let y = g.modpow(&x, p);
+    (Secret::new(x), y)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DsaPublicKey {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    pub y: BigUint,
+}
+
+impl DsaPublicKey {
+    pub fn verify(&self, r: &BigUint, s: &BigUint, hm: &BigUint) -> bool {
+        dsa_verify(&self.y, r, s, hm, &self.p, &self.q, &self.g)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DsaPrivateKey {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    x: Secret<BigUint>,
+}
+
+impl DsaPrivateKey {
+    pub fn generate(p: BigUint, q: BigUint, g: BigUint, rng: &mut impl rand::Rng) -> Self {
+        let (x, _) = dsa_keys(&p, &q, &g, rng);
+        Self { p, q, g, x }
+    }
+
+    pub fn from_x(p: BigUint, q: BigUint, g: BigUint, x: BigUint) -> Option<Self> {
+        let valid = x > 0u8.into() && x < q;
+        valid.then(|| Self {
+            p,
+            q,
+            g,
+            x: Secret::new(x),
+        })
+    }
+
+    pub fn x(&self) -> &BigUint {
+        self.x.expose()
+    }
+
+    pub fn public_key(&self) -> DsaPublicKey {
+        DsaPublicKey {
+            p: self.p.clone(),
+            q: self.q.clone(),
+            g: self.g.clone(),
+            y: self.g.modpow(self.x(), &self.p),
+        }
+    }
+
+    pub fn sign(&self, hm: &BigUint, rng: &mut impl rand::Rng) -> (BigUint, BigUint) {
+        dsa_sign(&self.x, hm, &self.p, &self.q, &self.g, rng)
+    }
 }
 
 pub fn dsa_sign<R: rand::Rng>(
-    x: &BigUint,
+    x: &Secret<BigUint>,
     hm: &BigUint,
     p: &BigUint,
     q: &BigUint,
@@ -34,6 +250,7 @@ pub fn dsa_sign<R: rand::Rng>(
 ) -> (BigUint, BigUint) {
     let mut k = rng.gen_biguint_range(&1u8.into(), &(q - 1u8));
     let mut r = g.modpow(&k, p) % q;
+    let x = x.expose();
     let mut s = div(&(hm + x * &r), &k, q).expect("k is not invertible");
     while r == 0u8.into() || s == 0u8.into() {
         k = rng.gen_biguint_range(&1u8.into(), &(q - 1u8));
@@ -43,7 +260,8 @@ pub fn dsa_sign<R: rand::Rng>(
     (r, s)
 }
 
-pub fn dsa_verify(
+// the v that a valid signature has for r, or none when r or s is out of range
+fn dsa_v(
     y: &BigUint,
     r: &BigUint,
     s: &BigUint,
@@ -51,19 +269,43 @@ pub fn dsa_verify(
     p: &BigUint,
     q: &BigUint,
     g: &BigUint,
-) -> bool {
+) -> Option<BigUint> {
     let zero = BigUint::from(0u8);
     if r == &zero || s == &zero || r >= q || s >= q {
-        return false;
+        return None;
     }
-    let w = match inv_egcd(s, q) {
-        Some(w) => w,
-        None => return false,
-    };
+    let w = inv_egcd(s, q)?;
     let u1 = hm * &w % q;
     let u2 = r * &w % q;
-    let v = (g.modpow(&u1, p) * y.modpow(&u2, p)) % p % q;
-    &v == r
+    let ctx = ModContext::new(p)?;
+    let (g, y) = (&ctx.residue(g), &ctx.residue(y));
+    Some(ctx.value(&ctx.multi_pow(&[(g, &u1), (y, &u2)])) % q)
+}
+
+pub fn dsa_verify(
+    y: &BigUint,
+    r: &BigUint,
+    s: &BigUint,
+    hm: &BigUint,
+    p: &BigUint,
+    q: &BigUint,
+    g: &BigUint,
+) -> bool {
+    dsa_v(y, r, s, hm, p, q, g).is_some_and(|v| eq_biguint(&v, r, q.to_bytes_be().len()))
+}
+
+// compares v and r with an early exit, so the time it takes tells how much of
+// a forged r is right
+pub fn dsa_verify_insecure(
+    y: &BigUint,
+    r: &BigUint,
+    s: &BigUint,
+    hm: &BigUint,
+    p: &BigUint,
+    q: &BigUint,
+    g: &BigUint,
+) -> bool {
+    dsa_v(y, r, s, hm, p, q, g).is_some_and(|v| &v == r)
 }
 
 pub fn dsa_privkey(k: &BigUint, r: &BigUint, s: &BigUint, hm: &BigUint, q: &BigUint) -> BigUint {
@@ -123,8 +365,9 @@ mod tests {
     use std::str::Lines;
 
     use super::*;
-    use crate::digest::sha1;
+    use crate::digest::{sha1, Sha256};
     use crate::encode::{from_ascii, to_hex};
+    use crate::prime::provable_pq;
     use rand::Rng;
 
     fn y() -> BigUint {
@@ -200,9 +443,11 @@ So be friendly, a matter of life and death, just like a etch-a-sketch
         let q = q();
         let g = g();
         let hm = hm();
-        assert!(dsa_verify(&y, &r, &s, &hm, &p, &q, &g));
-        assert!(!dsa_verify(&y, &(&r + 1u8), &s, &hm, &p, &q, &g));
-        assert!(!dsa_verify(&y, &r, &(&s + 1u8), &hm, &p, &q, &g));
+        for verify in [dsa_verify, dsa_verify_insecure] {
+            assert!(verify(&y, &r, &s, &hm, &p, &q, &g));
+            assert!(!verify(&y, &(&r + 1u8), &s, &hm, &p, &q, &g));
+            assert!(!verify(&y, &r, &(&s + 1u8), &hm, &p, &q, &g));
+        }
     }
 
     #[test]
@@ -265,4 +510,59 @@ So be friendly, a matter of life and death, just like a etch-a-sketch
         assert!(!dsa_verify(&y_, &r, &s, &hm, &p, &q, &g));
         assert!(!dsa_verify(&y_, &r_, &s_, &hm, &p, &q, &g));
     }
+
+    #[test]
+    fn fips_pq_works() {
+        let mut rng = rand::thread_rng();
+        let (p, q, seed) = fips_pq::<Sha256, 32>(1024, 160, &mut rng);
+        assert_eq!((p.bits(), q.bits()), (1024, 160));
+        assert!(validate_fips_pq::<Sha256, 32>(&p, &q, &seed, &mut rng));
+        let mut other = seed.clone();
+        other.counter += 1;
+        assert!(!validate_fips_pq::<Sha256, 32>(&p, &q, &other, &mut rng));
+        let mut other = seed.clone();
+        other.seed[0] ^= 1;
+        assert!(!validate_fips_pq::<Sha256, 32>(&p, &q, &other, &mut rng));
+
+        let g = fips_generator::<Sha256, 32>(&p, &q, &seed.seed, 1).expect("no generator");
+        assert!(validate_fips_generator::<Sha256, 32>(
+            &p, &q, &g, &seed.seed, 1, &mut rng
+        ));
+        assert!(!validate_fips_generator::<Sha256, 32>(
+            &p, &q, &g, &seed.seed, 2, &mut rng
+        ));
+        let g2 = g.modpow(&2u8.into(), &p);
+        assert!(!validate_fips_generator::<Sha256, 32>(
+            &p, &q, &g2, &seed.seed, 1, &mut rng
+        ));
+    }
+
+    #[test]
+    fn validate_dsa_parameters_works() {
+        let mut rng = rand::thread_rng();
+        let (p, q, g) = dsa_parameters(160, 1024, &mut rng);
+        assert!(validate_dsa_parameters(&p, &q, &g, &mut rng));
+        assert!(!validate_dsa_parameters(&p, &q, &1u8.into(), &mut rng));
+        assert!(!validate_dsa_parameters(&p, &(&q + 2u8), &g, &mut rng));
+        let h = BigUint::from(3u8);
+        let not_in_subgroup = h.modpow(&2u8.into(), &p);
+        assert!(!validate_dsa_parameters(&p, &q, &not_in_subgroup, &mut rng));
+    }
+
+    #[test]
+    fn validate_dsa_certificates_works() {
+        let mut rng = rand::thread_rng();
+        let (p, q, p_cert, q_cert) = provable_pq(1024, 160, &mut rng);
+        let h = rng.gen_biguint_range(&2u8.into(), &(&p - 1u8));
+        let g = h.modpow(&((&p - 1u8) / &q), &p);
+        assert!(validate_dsa_certificates(&p, &q, &g, &p_cert, &q_cert));
+        assert!(!validate_dsa_certificates(&p, &q, &g, &q_cert, &p_cert));
+        assert!(!validate_dsa_certificates(
+            &p,
+            &q,
+            &1u8.into(),
+            &p_cert,
+            &q_cert
+        ));
+    }
 }
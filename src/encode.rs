@@ -99,6 +99,88 @@ pub fn to_base64(bytes: &[u8]) -> String {
     res
 }
 
+pub fn to_pem(label: &str, der: &[u8]) -> String {
+    let b64 = to_base64(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in b64.as_bytes().chunks(64) {
+        pem.push_str(&to_ascii(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+// like from_base64, but rejects invalid input and drops the padding
+pub fn try_from_base64(b64: &str) -> Option<Vec<u8>> {
+    let data = b64.trim_end_matches('=');
+    let pad = b64.len() - data.len();
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '/';
+    if !b64.len().is_multiple_of(4) || pad > 2 || !data.chars().all(valid) {
+        return None;
+    }
+    let mut bytes = from_base64(b64);
+    bytes.truncate(bytes.len() - pad);
+    Some(bytes)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PemError {
+    NoBlocks,
+    MissingEnd(String),
+    LabelMismatch(String, String),
+    // encrypted (rfc 1421) blocks are not supported
+    Headers,
+    InvalidBase64,
+}
+
+// all the (label, der) blocks, text between the blocks is ignored
+pub fn decode_pem_all(pem: &str) -> Result<Vec<(String, Vec<u8>)>, PemError> {
+    let armor = |line: &str, kind: &str| {
+        let label = line.trim().strip_prefix("-----")?.strip_suffix("-----")?;
+        Some(String::from(label.strip_prefix(kind)?))
+    };
+    let mut blocks = vec![];
+    let mut lines = pem.lines();
+    while let Some(line) = lines.next() {
+        let label = match armor(line, "BEGIN ") {
+            Some(label) => label,
+            None => continue,
+        };
+        let mut b64 = String::new();
+        loop {
+            let line = lines.next().ok_or(PemError::MissingEnd(label.clone()))?;
+            if let Some(end) = armor(line, "END ") {
+                if end != label {
+                    return Err(PemError::LabelMismatch(label, end));
+                }
+                break;
+            }
+            if line.contains(':') {
+                return Err(PemError::Headers);
+            }
+            b64.extend(line.split_whitespace());
+        }
+        let der = try_from_base64(&b64).ok_or(PemError::InvalidBase64)?;
+        blocks.push((label, der));
+    }
+    Ok(blocks)
+}
+
+pub fn decode_pem(pem: &str) -> Result<(String, Vec<u8>), PemError> {
+    decode_pem_all(pem)?
+        .into_iter()
+        .next()
+        .ok_or(PemError::NoBlocks)
+}
+
+// the first block with the label
+pub fn from_pem(label: &str, pem: &str) -> Option<Vec<u8>> {
+    decode_pem_all(pem)
+        .ok()?
+        .into_iter()
+        .find_map(|(l, der)| (l == label).then_some(der))
+}
+
 pub fn from_ascii(str: &str) -> Vec<u8> {
     str.bytes().collect()
 }
@@ -176,4 +258,67 @@ mod tests {
             String::from("SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t")
         );
     }
+
+    #[test]
+    fn pem_works() {
+        for len in [0, 1, 2, 3, 47, 48, 49, 100] {
+            let der: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let pem = to_pem("TEST", &der);
+            assert!(pem.lines().all(|l| l.len() <= 64));
+            assert_eq!(from_pem("TEST", &pem), Some(der));
+            assert_eq!(from_pem("OTHER", &pem), None);
+        }
+        assert_eq!(
+            from_pem("TEST", "-----BEGIN TEST-----\nTW*u\n-----END TEST-----"),
+            None
+        );
+    }
+
+    #[test]
+    fn try_from_base64_works() {
+        assert_eq!(try_from_base64("TWFueQ=="), Some(from_ascii("Many")));
+        assert_eq!(try_from_base64("TWFu"), Some(from_ascii("Man")));
+        assert_eq!(try_from_base64(""), Some(vec![]));
+        assert_eq!(try_from_base64("TWFueQ="), None);
+        assert_eq!(try_from_base64("TW=ueQ=="), None);
+        assert_eq!(try_from_base64("TWFu===="), None);
+    }
+
+    #[test]
+    fn decode_pem_works() {
+        let pem = format!(
+            "comment\n{}between\n{}",
+            to_pem("A", &[1, 2, 3]),
+            to_pem("B KEY", &[4])
+        );
+        let blocks = decode_pem_all(&pem).expect("valid pem");
+        assert_eq!(
+            blocks,
+            vec![
+                (String::from("A"), vec![1, 2, 3]),
+                (String::from("B KEY"), vec![4])
+            ]
+        );
+        assert_eq!(decode_pem(&pem), Ok((String::from("A"), vec![1, 2, 3])));
+        assert_eq!(from_pem("B KEY", &pem), Some(vec![4]));
+
+        let err = |pem: &str| decode_pem(pem).expect_err("invalid pem");
+        assert_eq!(err("no armor"), PemError::NoBlocks);
+        assert_eq!(
+            err("-----BEGIN A-----\nAQID\n"),
+            PemError::MissingEnd(String::from("A"))
+        );
+        assert_eq!(
+            err("-----BEGIN A-----\nAQID\n-----END B-----"),
+            PemError::LabelMismatch(String::from("A"), String::from("B"))
+        );
+        assert_eq!(
+            err("-----BEGIN A-----\nProc-Type: 4,ENCRYPTED\n\nAQID\n-----END A-----"),
+            PemError::Headers
+        );
+        assert_eq!(
+            err("-----BEGIN A-----\nAQI\n-----END A-----"),
+            PemError::InvalidBase64
+        );
+    }
 }
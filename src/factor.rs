@@ -0,0 +1,470 @@
+use crate::math::{gcd, inv_egcd, nth_root};
+use crate::prime::{mrp_check, wheel_primes};
+use num_bigint::{BigUint, RandBigInt};
+use std::time::{Duration, Instant};
+
+// a non-trivial factor out of a gcd
+fn split(g: BigUint, n: &BigUint) -> Option<BigUint> {
+    (g > 1u8.into() && &g < n).then_some(g)
+}
+
+fn sub(a: &BigUint, b: &BigUint, n: &BigUint) -> BigUint {
+    (a + n - b % n) % n
+}
+
+pub fn is_prime(n: &BigUint, rng: &mut impl rand::Rng) -> bool {
+    for p in wheel_primes(256) {
+        if n == &p.into() {
+            return true;
+        }
+        if n % p == 0u8.into() {
+            return false;
+        }
+    }
+    match n < &BigUint::from(1u32 << 16) {
+        true => n > &1u8.into(),
+        false => mrp_check(n, 32, rng),
+    }
+}
+
+// the largest power of p up to bound
+fn prime_power(p: usize, bound: usize) -> BigUint {
+    let mut q = p;
+    while q <= bound / p {
+        q *= p;
+    }
+    q.into()
+}
+
+// fermat: n = a^2 - b^2 = (a - b)(a + b), fast when |p - q| is small
+pub fn fermat(n: &BigUint, max_iters: u64) -> Option<BigUint> {
+    let mut a = nth_root(n.clone(), 2);
+    if &(&a * &a) < n {
+        a += 1u8;
+    }
+    for _ in 0..max_iters {
+        let b2 = &a * &a - n;
+        if b2 == 0u8.into() {
+            return split(a, n);
+        }
+        let b = nth_root(b2.clone(), 2);
+        if &b * &b == b2 {
+            return split(a - b, n);
+        }
+        a += 1u8;
+    }
+    None
+}
+
+// pollard p - 1, finds p when p - 1 is bound-smooth
+pub fn pollard_pm1(n: &BigUint, bound: usize) -> Option<BigUint> {
+    let mut a = BigUint::from(2u8);
+    for (i, p) in wheel_primes(bound).into_iter().enumerate() {
+        a = a.modpow(&prime_power(p, bound), n);
+        // checking only now and then, which can overshoot when all the
+        // factors are smooth
+        if i % 64 == 63 {
+            let g = gcd(&(&a + n - 1u8), n);
+            if g != 1u8.into() {
+                return split(g, n);
+            }
+        }
+    }
+    split(gcd(&(&a + n - 1u8), n), n)
+}
+
+// v_m of the lucas sequence v_0 = 2, v_1 = a, v_k+1 = a v_k - v_k-1
+fn lucas_v(a: &BigUint, m: &BigUint, n: &BigUint) -> BigUint {
+    let (mut x, mut y) = (a.clone(), sub(&(a * a), &2u8.into(), n));
+    for i in (0..m.bits() - 1).rev() {
+        let xy = sub(&(&x * &y), a, n);
+        if m.bit(i) {
+            x = xy;
+            y = sub(&(&y * &y), &2u8.into(), n);
+        } else {
+            y = xy;
+            x = sub(&(&x * &x), &2u8.into(), n);
+        }
+    }
+    x
+}
+
+// williams p + 1, finds p when p + 1 is bound-smooth and a^2 - 4 isn't a square
+// mod p, which each seed satisfies with probability 1/2
+pub fn williams_pp1(n: &BigUint, bound: usize, seeds: &[u32]) -> Option<BigUint> {
+    let primes = wheel_primes(bound);
+    for a in seeds {
+        let mut v = BigUint::from(*a);
+        for p in &primes {
+            v = lucas_v(&v, &prime_power(*p, bound), n);
+        }
+        if let Some(f) = split(gcd(&sub(&v, &2u8.into(), n), n), n) {
+            return Some(f);
+        }
+    }
+    None
+}
+
+// brent's variant of pollard rho, with the gcds batched over m steps
+pub fn brent_rho(n: &BigUint, max_iters: u64, rng: &mut impl rand::Rng) -> Option<BigUint> {
+    if !n.bit(0) {
+        return split(2u8.into(), n);
+    }
+    let m = 128;
+    let c = rng.gen_biguint_range(&1u8.into(), n);
+    let f = |x: &BigUint| (x * x + &c) % n;
+    let mut y = rng.gen_biguint_below(n);
+    let (mut x, mut ys) = (y.clone(), y.clone());
+    let (mut r, mut q, mut g) = (1u64, BigUint::from(1u8), BigUint::from(1u8));
+    let mut iters = 0;
+    while g == 1u8.into() {
+        x = y.clone();
+        for _ in 0..r {
+            y = f(&y);
+        }
+        let mut k = 0;
+        while k < r && g == 1u8.into() {
+            ys = y.clone();
+            for _ in 0..m.min(r - k) {
+                y = f(&y);
+                q = q * sub(&x, &y, n) % n;
+            }
+            g = gcd(&q, n);
+            k += m;
+        }
+        r *= 2;
+        iters += r;
+        if iters > max_iters {
+            return None;
+        }
+    }
+    if g == *n {
+        // the batch overshot, step through it one by one
+        loop {
+            ys = f(&ys);
+            g = gcd(&sub(&x, &ys, n), n);
+            if g > 1u8.into() {
+                break;
+            }
+        }
+    }
+    split(g, n)
+}
+
+// a point (x : z) on the montgomery curve b y^2 = x^3 + a x^2 + x
+struct Curve<'a> {
+    n: &'a BigUint,
+    // (a + 2) / 4
+    a24: BigUint,
+}
+
+impl Curve<'_> {
+    fn double(&self, (x, z): &(BigUint, BigUint)) -> (BigUint, BigUint) {
+        let n = self.n;
+        let s = (x + z) % n;
+        let d = sub(x, z, n);
+        let (s2, d2) = (&s * &s % n, &d * &d % n);
+        let t = sub(&s2, &d2, n);
+        (&s2 * &d2 % n, t.clone() * ((d2 + &self.a24 * t) % n) % n)
+    }
+
+    // p + q given p - q
+    fn add(
+        &self,
+        (xp, zp): &(BigUint, BigUint),
+        (xq, zq): &(BigUint, BigUint),
+        (xd, zd): &(BigUint, BigUint),
+    ) -> (BigUint, BigUint) {
+        let n = self.n;
+        let u = sub(xp, zp, n) * ((xq + zq) % n) % n;
+        let v = (xp + zp) % n * sub(xq, zq, n) % n;
+        let (s, d) = ((&u + &v) % n, sub(&u, &v, n));
+        (zd * (&s * &s % n) % n, xd * (&d * &d % n) % n)
+    }
+
+    fn mul(&self, pt: &(BigUint, BigUint), k: &BigUint) -> (BigUint, BigUint) {
+        let (mut r0, mut r1) = (pt.clone(), self.double(pt));
+        for i in (0..k.bits() - 1).rev() {
+            if k.bit(i) {
+                r0 = self.add(&r0, &r1, pt);
+                r1 = self.double(&r1);
+            } else {
+                r1 = self.add(&r0, &r1, pt);
+                r0 = self.double(&r0);
+            }
+        }
+        r0
+    }
+}
+
+// lenstra's elliptic curve method, stage 1 only, on suyama-parametrized curves.
+// finds p when the order of a random curve mod p is b1-smooth
+pub fn ecm(n: &BigUint, b1: usize, curves: usize, rng: &mut impl rand::Rng) -> Option<BigUint> {
+    // too small to draw sigma from [6, n), trial division does
+    if n <= &6u8.into() {
+        let mut ds = (2u8..6).map(BigUint::from);
+        return ds.find(|d| d < n && n % d == 0u8.into());
+    }
+    let ks: Vec<_> = wheel_primes(b1)
+        .into_iter()
+        .map(|p| prime_power(p, b1))
+        .collect();
+    for _ in 0..curves {
+        let sigma = rng.gen_biguint_range(&6u8.into(), n);
+        let u = sub(&(&sigma * &sigma), &5u8.into(), n);
+        let v = 4u8 * sigma % n;
+        let (u3, v_u) = (u.modpow(&3u8.into(), n), sub(&v, &u, n));
+        let num = v_u.modpow(&3u8.into(), n) * ((3u8 * &u + &v) % n) % n;
+        let den = 16u8 * &u3 * &v % n;
+        let a24 = match inv_egcd(&den, n) {
+            Some(inv) => num * inv % n,
+            None => match split(gcd(&den, n), n) {
+                Some(f) => return Some(f),
+                None => continue,
+            },
+        };
+        let curve = Curve { n, a24 };
+        let mut pt = (u3, v.modpow(&3u8.into(), n));
+        for k in &ks {
+            pt = curve.mul(&pt, k);
+        }
+        if let Some(f) = split(gcd(&pt.1, n), n) {
+            return Some(f);
+        }
+    }
+    None
+}
+
+// levels of pairwise products, from the leaves up to the single root
+fn product_tree(leaves: &[BigUint]) -> Vec<Vec<BigUint>> {
+    let mut tree = vec![leaves.to_vec()];
+    while tree[tree.len() - 1].len() > 1 {
+        let level = tree[tree.len() - 1]
+            .chunks(2)
+            .map(|pair| pair.iter().product())
+            .collect();
+        tree.push(level);
+    }
+    tree
+}
+
+// bernstein's batch gcd: gcd(n_i, product of all the other moduli) for every i,
+// with a remainder tree of prod mod n_i^2 down from the root
+pub fn batch_gcd(moduli: &[BigUint]) -> Vec<BigUint> {
+    if moduli.is_empty() {
+        return vec![];
+    }
+    let tree = product_tree(moduli);
+    let mut rems = tree[tree.len() - 1].clone();
+    for level in tree.iter().rev().skip(1) {
+        rems = level
+            .iter()
+            .enumerate()
+            .map(|(i, x)| &rems[i / 2] % (x * x))
+            .collect();
+    }
+    let mut gcds: Vec<_> = moduli
+        .iter()
+        .zip(rems)
+        .map(|(n, r)| gcd(&(r / n), n))
+        .collect();
+    // all of n's primes are shared, or n is repeated, the pairwise gcds tell which
+    for i in 0..moduli.len() {
+        if gcds[i] == moduli[i] {
+            let n = &moduli[i];
+            let mut pairs = moduli.iter().map(|m| gcd(n, m));
+            gcds[i] = pairs
+                .find(|g| g != n && g > &1u8.into())
+                .unwrap_or_else(|| n.clone());
+        }
+    }
+    gcds
+}
+
+// the prime factors of n with multiplicity and, when the budget runs out, the
+// composite cofactors that couldn't be split. 0 has no factorization and comes
+// back as such a cofactor
+pub fn factorize(
+    n: &BigUint,
+    budget: Duration,
+    rng: &mut impl rand::Rng,
+) -> (Vec<BigUint>, Vec<BigUint>) {
+    if n == &0u8.into() {
+        return (vec![], vec![n.clone()]);
+    }
+    let deadline = Instant::now() + budget;
+    let (mut primes, mut composites) = (vec![], vec![]);
+    let mut n = n.clone();
+    for p in wheel_primes(1 << 12) {
+        while &n % p == 0u8.into() {
+            n /= p;
+            primes.push(p.into());
+        }
+    }
+    let mut todo = vec![n];
+    while let Some(n) = todo.pop() {
+        if n == 1u8.into() {
+            continue;
+        }
+        if is_prime(&n, rng) {
+            primes.push(n);
+            continue;
+        }
+        // cheap and targeted first, then ecm with growing bounds
+        let mut f = fermat(&n, 1 << 12)
+            .or_else(|| pollard_pm1(&n, 1 << 14))
+            .or_else(|| williams_pp1(&n, 1 << 12, &[3, 5, 7]));
+        let mut b1 = 1 << 8;
+        while f.is_none() && Instant::now() < deadline {
+            f = brent_rho(&n, 1 << 16, rng).or_else(|| ecm(&n, b1, 8, rng));
+            b1 = (b1 * 2).min(1 << 20);
+        }
+        match f {
+            Some(f) => {
+                todo.push(&n / &f);
+                todo.push(f);
+            }
+            None => composites.push(n),
+        }
+    }
+    primes.sort();
+    (primes, composites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prime::mr_prime;
+    use rand::seq::SliceRandom;
+
+    // a prime p with p + d smooth
+    fn smooth_prime(bits: u64, d: i8, rng: &mut impl rand::Rng) -> BigUint {
+        let mut small = wheel_primes(1000).split_off(1);
+        loop {
+            // distinct primes, so that bound 1000 covers all of them
+            small.shuffle(rng);
+            let mut m = BigUint::from(2u8);
+            for p in &small {
+                if m.bits() >= bits {
+                    break;
+                }
+                m *= *p;
+            }
+            let p = match d {
+                1 => m - 1u8,
+                _ => m + 1u8,
+            };
+            if is_prime(&p, rng) {
+                return p;
+            }
+        }
+    }
+
+    #[test]
+    fn is_prime_works() {
+        let mut rng = rand::thread_rng();
+        let primes: Vec<u32> = vec![2, 3, 5, 251, 257, 65537, 2147483647];
+        for p in primes {
+            assert!(is_prime(&p.into(), &mut rng), "{}", p);
+        }
+        for c in [0u64, 1, 4, 9, 255, 257 * 263, 65537 * 65539] {
+            assert!(!is_prime(&c.into(), &mut rng), "{}", c);
+        }
+    }
+
+    #[test]
+    fn fermat_works() {
+        let mut rng = rand::thread_rng();
+        let p = mr_prime(256, 10, &mut rng);
+        let mut q = &p + 2u8;
+        while !is_prime(&q, &mut rng) {
+            q += 2u8;
+        }
+        assert_eq!(fermat(&(&p * &q), 10), Some(p.clone()));
+        assert_eq!(fermat(&(&p * &p), 1), Some(p));
+    }
+
+    #[test]
+    fn pollard_pm1_works() {
+        let mut rng = rand::thread_rng();
+        let p = smooth_prime(128, -1, &mut rng);
+        let q = mr_prime(128, 10, &mut rng);
+        assert_eq!(pollard_pm1(&(&p * &q), 1000), Some(p));
+    }
+
+    #[test]
+    fn williams_pp1_works() {
+        let mut rng = rand::thread_rng();
+        let p = smooth_prime(128, 1, &mut rng);
+        let q = mr_prime(128, 10, &mut rng);
+        let seeds: Vec<u32> = (3..20).collect();
+        assert_eq!(williams_pp1(&(&p * &q), 1000, &seeds), Some(p));
+    }
+
+    #[test]
+    fn brent_rho_works() {
+        let mut rng = rand::thread_rng();
+        let p = mr_prime(28, 10, &mut rng);
+        let q = mr_prime(128, 10, &mut rng);
+        let f = brent_rho(&(&p * &q), 1 << 20, &mut rng);
+        assert_eq!(f, Some(p));
+    }
+
+    #[test]
+    fn ecm_works() {
+        let mut rng = rand::thread_rng();
+        let p = mr_prime(40, 10, &mut rng);
+        let q = mr_prime(128, 10, &mut rng);
+        let f = ecm(&(&p * &q), 2000, 200, &mut rng);
+        assert_eq!(f, Some(p));
+        for (n, f) in [
+            (0u8, None),
+            (1, None),
+            (4, Some(2u8)),
+            (5, None),
+            (6, Some(2)),
+        ] {
+            assert_eq!(ecm(&n.into(), 2000, 1, &mut rng), f.map(BigUint::from));
+        }
+    }
+
+    #[test]
+    fn batch_gcd_works() {
+        let ns: Vec<BigUint> = [3u32 * 5, 5 * 7, 11 * 13, 13 * 17, 19 * 23, 3 * 5]
+            .map(BigUint::from)
+            .to_vec();
+        let gcds: Vec<BigUint> = [5u32, 5, 13, 13, 1, 5].map(BigUint::from).to_vec();
+        // 15 appears twice, so both of its primes are shared
+        assert_eq!(batch_gcd(&ns), gcds);
+        assert!(batch_gcd(&[]).is_empty());
+        assert_eq!(batch_gcd(&ns[..1]), vec![1u8.into()]);
+    }
+
+    #[test]
+    fn factorize_works() {
+        let mut rng = rand::thread_rng();
+        let mut expected: Vec<BigUint> = vec![
+            2u8.into(),
+            2u8.into(),
+            mr_prime(20, 10, &mut rng),
+            mr_prime(36, 10, &mut rng),
+            smooth_prime(100, -1, &mut rng),
+            mr_prime(100, 10, &mut rng),
+        ];
+        let n = expected.iter().product();
+        expected.sort();
+        let (primes, composites) = factorize(&n, Duration::from_secs(60), &mut rng);
+        assert_eq!(primes, expected);
+        assert!(composites.is_empty());
+
+        let n = mr_prime(100, 10, &mut rng) * mr_prime(100, 10, &mut rng);
+        let (primes, composites) = factorize(&n, Duration::ZERO, &mut rng);
+        assert!(primes.is_empty());
+        assert_eq!(composites, vec![n]);
+
+        let budget = Duration::from_secs(1);
+        assert_eq!(factorize(&1u8.into(), budget, &mut rng), (vec![], vec![]));
+        let zero = BigUint::from(0u8);
+        assert_eq!(factorize(&zero, budget, &mut rng), (vec![], vec![zero]));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::math::{index_calculus, index_calculus_bits, inv_egcd, prime_order_log, sqrt_mod};
+use crate::modular::{ModContext, Residue};
+use num_bigint::{BigUint, RandBigInt};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+// a finite abelian group, written multiplicatively, for the discrete log
+// algorithms and the small subgroup attacks to work in
+pub trait Group: Sized {
+    type Element: Clone + Debug + Eq + Hash + Send + Sync;
+
+    fn identity(&self) -> Self::Element;
+
+    fn op(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+
+    // a multiple of the order of every element
+    fn order(&self) -> BigUint;
+
+    // some bits of a to steer the pseudo-random walks of rho and the kangaroos
+    fn hash(&self, a: &Self::Element) -> u64;
+
+    fn random(&self, rng: &mut impl rand::Rng) -> Self::Element;
+
+    // a^n by square and multiply
+    fn exp(&self, a: &Self::Element, n: &BigUint) -> Self::Element {
+        let mut x = self.identity();
+        for i in (0..n.bits()).rev() {
+            x = self.op(&x, &x);
+            if n.bit(i) {
+                x = self.op(&x, a);
+            }
+        }
+        x
+    }
+
+    // log h to a base g of prime order q, with the generic algorithms unless
+    // the group has a quicker way
+    fn prime_order_log(
+        &self,
+        g: &Self::Element,
+        h: &Self::Element,
+        q: &BigUint,
+    ) -> Option<BigUint> {
+        prime_order_log(self, g, h, q)
+    }
+}
+
+// z_p^* or one of its subgroups, with a multiple of its order
+#[derive(Clone, Debug)]
+pub struct ModGroup {
+    pub p: BigUint,
+    pub order: BigUint,
+}
+
+impl Group for ModGroup {
+    type Element = BigUint;
+
+    fn identity(&self) -> BigUint {
+        1u8.into()
+    }
+
+    fn op(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b % &self.p
+    }
+
+    fn inverse(&self, a: &BigUint) -> BigUint {
+        inv_egcd(a, &self.p).expect("p is a prime")
+    }
+
+    fn order(&self) -> BigUint {
+        self.order.clone()
+    }
+
+    fn hash(&self, a: &BigUint) -> u64 {
+        a.iter_u64_digits().next().unwrap_or(0)
+    }
+
+    fn random(&self, rng: &mut impl rand::Rng) -> BigUint {
+        rng.gen_biguint_range(&1u8.into(), &self.p)
+    }
+
+    fn exp(&self, a: &BigUint, n: &BigUint) -> BigUint {
+        a.modpow(n, &self.p)
+    }
+
+    // index calculus when it beats rho
+    fn prime_order_log(&self, g: &BigUint, h: &BigUint, q: &BigUint) -> Option<BigUint> {
+        let coprime = (&self.p - 1u8) / q % q != 0u8.into();
+        let cheaper = index_calculus_bits(&self.p) < q.bits() as f64 / 2.0;
+        if q.bits() > 40 && coprime && cheaper {
+            return index_calculus(&self.p, g, h, q).filter(|x| self.exp(g, x) == *h);
+        }
+        prime_order_log(self, g, h, q)
+    }
+}
+
+// z_p^* like ModGroup, with the elements kept in montgomery form so that the
+// walks of rho and the kangaroos multiply without dividing
+#[derive(Clone, Debug)]
+pub struct MontGroup {
+    pub ctx: ModContext,
+    pub order: BigUint,
+}
+
+impl MontGroup {
+    pub fn new(p: &BigUint, order: &BigUint) -> Self {
+        MontGroup {
+            ctx: ModContext::new(p).expect("p is an odd prime"),
+            order: order.clone(),
+        }
+    }
+}
+
+impl Group for MontGroup {
+    type Element = Residue;
+
+    fn identity(&self) -> Residue {
+        self.ctx.one()
+    }
+
+    fn op(&self, a: &Residue, b: &Residue) -> Residue {
+        self.ctx.mul(a, b)
+    }
+
+    fn inverse(&self, a: &Residue) -> Residue {
+        let p = self.ctx.modulus();
+        self.ctx
+            .residue(&inv_egcd(&self.ctx.value(a), p).expect("p is a prime"))
+    }
+
+    fn order(&self) -> BigUint {
+        self.order.clone()
+    }
+
+    fn hash(&self, a: &Residue) -> u64 {
+        a.low()
+    }
+
+    fn random(&self, rng: &mut impl rand::Rng) -> Residue {
+        self.ctx
+            .residue(&rng.gen_biguint_range(&1u8.into(), self.ctx.modulus()))
+    }
+
+    fn exp(&self, a: &Residue, n: &BigUint) -> Residue {
+        self.ctx.pow(a, n)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Point {
+    Infinity,
+    Affine(BigUint, BigUint),
+}
+
+// the points of y^2 = x^3 + a x + b over f_p, with their number. the group law
+// never looks at b, so points of a curve with another b go through it unnoticed
+#[derive(Clone, Debug)]
+pub struct Curve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    pub order: BigUint,
+}
+
+impl Curve {
+    fn rhs(&self, x: &BigUint) -> BigUint {
+        (x * x * x + &self.a * x + &self.b) % &self.p
+    }
+
+    pub fn contains(&self, pt: &Point) -> bool {
+        match pt {
+            Point::Infinity => true,
+            Point::Affine(x, y) => y * y % &self.p == self.rhs(x),
+        }
+    }
+
+    // a point with x as its first coordinate, if there is one
+    pub fn lift_x(&self, x: &BigUint) -> Option<Point> {
+        let y = sqrt_mod(&self.rhs(x), &self.p)?;
+        Some(Point::Affine(x % &self.p, y))
+    }
+}
+
+impl Group for Curve {
+    type Element = Point;
+
+    fn identity(&self) -> Point {
+        Point::Infinity
+    }
+
+    fn op(&self, a: &Point, b: &Point) -> Point {
+        let (x1, y1, x2, y2) = match (a, b) {
+            (Point::Infinity, _) => return b.clone(),
+            (_, Point::Infinity) => return a.clone(),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => (x1, y1, x2, y2),
+        };
+        let p = &self.p;
+        let inv = |x: &BigUint| inv_egcd(x, p).expect("p is a prime");
+        let l = if x1 == x2 {
+            if (y1 + y2) % p == 0u8.into() {
+                return Point::Infinity;
+            }
+            // the tangent
+            (x1 * x1 * 3u8 + &self.a) * inv(&(y1 * 2u8)) % p
+        } else {
+            (y2 + p - y1) * inv(&((x2 + p - x1) % p)) % p
+        };
+        let x3 = (&l * &l + p * 2u8 - x1 - x2) % p;
+        let y3 = (l * ((x1 + p - &x3) % p) + p - y1) % p;
+        Point::Affine(x3, y3)
+    }
+
+    fn inverse(&self, a: &Point) -> Point {
+        match a {
+            Point::Infinity => Point::Infinity,
+            Point::Affine(x, y) => Point::Affine(x.clone(), (&self.p - y) % &self.p),
+        }
+    }
+
+    fn order(&self) -> BigUint {
+        self.order.clone()
+    }
+
+    fn hash(&self, a: &Point) -> u64 {
+        let low = |x: &BigUint| x.iter_u64_digits().next().unwrap_or(0);
+        match a {
+            Point::Infinity => 0,
+            Point::Affine(x, y) => low(x) ^ low(y).rotate_left(32),
+        }
+    }
+
+    fn random(&self, rng: &mut impl rand::Rng) -> Point {
+        loop {
+            if let Some(pt) = self.lift_x(&rng.gen_biguint_below(&self.p)) {
+                return match rng.gen() {
+                    true => self.inverse(&pt),
+                    false => pt,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dh::{get_privkey_mod_from_crt, subgroups};
+    use crate::math::{crt, gcd, kangaroo, pohlig_hellman};
+    use crate::prime::pq;
+
+    fn num(s: &[u8]) -> BigUint {
+        BigUint::parse_bytes(s, 10).expect("not a number")
+    }
+
+    // the curve of cryptopals 59, with b changed to give curves of smooth order
+    fn curve(b: u32, order: &[u8]) -> Curve {
+        let p = num(b"233970423115425145524320034830162017933");
+        Curve {
+            a: &p - 95051u32,
+            p,
+            b: b.into(),
+            order: num(order),
+        }
+    }
+
+    #[test]
+    fn mont_group_works() {
+        let mut rng = rand::thread_rng();
+        let (p, q) = &pq(256, 128, 5, &mut rng);
+        let group = MontGroup::new(p, &(p - 1u8));
+        let g = &rng.gen_biguint_range(&2u8.into(), p);
+        let mg = &group.ctx.residue(g);
+        let x = rng.gen_biguint_below(q);
+        assert_eq!(group.ctx.value(&group.exp(mg, &x)), g.modpow(&x, p));
+        let prod = group.op(mg, &group.inverse(mg));
+        assert_eq!(prod, group.identity());
+        let lower = &(BigUint::from(1u8) << 40);
+        let upper = &(lower + (1u64 << 32));
+        let x = rng.gen_biguint_range(lower, upper);
+        let y = &group.exp(mg, &x);
+        let x2 = kangaroo(&group, mg, y, lower, upper, 4, |_, _| {}).expect("did not find x");
+        assert_eq!(x2, x);
+    }
+
+    #[test]
+    fn curve_works() {
+        let mut rng = rand::thread_rng();
+        let e = curve(11279326, b"233970423115425145498902418297807005944");
+        let q = num(b"29246302889428143187362802287225875743");
+        let g = Point::Affine(182u8.into(), num(b"85518893674295321206118380980485522083"));
+        assert!(e.contains(&g));
+        assert_eq!(e.exp(&g, &q), Point::Infinity);
+        let (a, b) = (rng.gen_biguint_below(&q), rng.gen_biguint_below(&q));
+        let sum = e.op(&e.exp(&g, &a), &e.exp(&g, &b));
+        assert_eq!(sum, e.exp(&g, &(a + &b)));
+        assert!(e.contains(&sum));
+        assert_eq!(e.op(&sum, &e.inverse(&sum)), Point::Infinity);
+        let r = e.random(&mut rng);
+        assert!(e.contains(&r));
+        assert_eq!(e.exp(&r, &e.order), Point::Infinity);
+    }
+
+    #[test]
+    fn discrete_log_on_curve_works() {
+        let mut rng = rand::thread_rng();
+        let e = curve(210, b"233970423115425145550826547352470124412");
+        // a point whose order divides 2^2 3 11 23 31 89 4999
+        let order: Vec<(BigUint, u32)> = [
+            (2u32, 2),
+            (3, 1),
+            (11, 1),
+            (23, 1),
+            (31, 1),
+            (89, 1),
+            (4999, 1),
+        ]
+        .iter()
+        .map(|(q, e)| ((*q).into(), *e))
+        .collect();
+        let m: BigUint = order.iter().map(|(q, e)| q.pow(*e)).product();
+        let g = e.exp(&e.random(&mut rng), &(&e.order / &m));
+        let x = rng.gen_biguint_below(&m);
+        let h = e.exp(&g, &x);
+        let x2 = pohlig_hellman(&e, &g, &h, &order).expect("h is a multiple of g");
+        assert_eq!(e.exp(&g, &x2), h);
+    }
+
+    #[test]
+    fn invalid_curve_attack_works() {
+        let mut rng = rand::thread_rng();
+        let e = curve(11279326, b"233970423115425145498902418297807005944");
+        let q = num(b"29246302889428143187362802287225875743");
+        let x = rng.gen_biguint_range(&1u8.into(), &q);
+        // the victim multiplies whatever point it gets by its secret
+        let sign = |pt: &Point| e.exp(pt, &x);
+        let invalid = [
+            curve(210, b"233970423115425145550826547352470124412"),
+            curve(504, b"233970423115425145544350131142039591210"),
+            curve(727, b"233970423115425145545378039958152057148"),
+        ];
+        let mut residues = vec![];
+        let mut modulus = BigUint::from(1u8);
+        for e2 in &invalid {
+            let subs: Vec<_> = subgroups(e2, &1u8.into(), 16, &mut rng)
+                .into_iter()
+                .filter(|(f, _)| gcd(&(*f).into(), &modulus) == 1u8.into())
+                .collect();
+            let (r, m) = get_privkey_mod_from_crt(e2, &subs, sign).expect("x mod m not found");
+            modulus *= &m;
+            residues.push((r, m));
+        }
+        assert!(modulus > q);
+        assert_eq!(crt(&residues).0, x);
+    }
+}
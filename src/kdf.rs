@@ -0,0 +1,175 @@
+use crate::digest::{hmac, Hasher, Sha256};
+use crate::xor::xor_slice;
+
+// rfc 5869
+pub fn hkdf_extract<H: Hasher<N>, const N: usize>(salt: &[u8], ikm: &[u8]) -> [u8; N] {
+    match salt.is_empty() {
+        true => hmac::<H, N>(&[0; N], ikm),
+        false => hmac::<H, N>(salt, ikm),
+    }
+}
+
+pub fn hkdf_expand<H: Hasher<N>, const N: usize>(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    assert!(len <= 255 * N, "output too long");
+    let mut okm = vec![];
+    let mut t = vec![];
+    for i in 1..=len.div_ceil(N) as u8 {
+        t = Vec::from(hmac::<H, N>(prk, &[&t, info, &[i]].concat()));
+        okm.extend(&t);
+    }
+    okm.truncate(len);
+    okm
+}
+
+pub fn hkdf<H: Hasher<N>, const N: usize>(
+    salt: &[u8],
+    ikm: &[u8],
+    info: &[u8],
+    len: usize,
+) -> Vec<u8> {
+    hkdf_expand::<H, N>(&hkdf_extract::<H, N>(salt, ikm), info, len)
+}
+
+// rfc 8018
+pub fn pbkdf2<H: Hasher<N>, const N: usize>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Vec<u8> {
+    assert!(iterations > 0, "at least one iteration is needed");
+    let mut dk = vec![];
+    for i in 1..=len.div_ceil(N) as u32 {
+        let mut u = hmac::<H, N>(password, &[salt, &i.to_be_bytes()].concat());
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac::<H, N>(password, &u);
+            xor_slice(&mut t, &u);
+        }
+        dk.extend(t);
+    }
+    dk.truncate(len);
+    dk
+}
+
+fn salsa20_8(b: &mut [u32; 16]) {
+    let mut x = *b;
+    let mut qr = |a: usize, b: usize, c: usize, d: usize| {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    };
+    for _ in 0..4 {
+        // columns
+        qr(0, 4, 8, 12);
+        qr(5, 9, 13, 1);
+        qr(10, 14, 2, 6);
+        qr(15, 3, 7, 11);
+        // rows
+        qr(0, 1, 2, 3);
+        qr(5, 6, 7, 4);
+        qr(10, 11, 8, 9);
+        qr(15, 12, 13, 14);
+    }
+    for i in 0..16 {
+        b[i] = b[i].wrapping_add(x[i]);
+    }
+}
+
+// b consists of 2r 16-word blocks
+fn block_mix(b: &[u32]) -> Vec<u32> {
+    let r = b.len() / 32;
+    let mut x: [u32; 16] = b[b.len() - 16..].try_into().expect("invalid bs");
+    let mut y = vec![0; b.len()];
+    for (i, chk) in b.chunks_exact(16).enumerate() {
+        xor_slice(&mut x, chk);
+        salsa20_8(&mut x);
+        // even blocks first, then odd
+        let j = i / 2 + (i % 2) * r;
+        y[j * 16..(j + 1) * 16].copy_from_slice(&x);
+    }
+    y
+}
+
+fn ro_mix(b: &mut Vec<u32>, n: usize) {
+    let mut v = Vec::with_capacity(n);
+    for _ in 0..n {
+        let x = block_mix(b);
+        v.push(std::mem::replace(b, x));
+    }
+    for _ in 0..n {
+        let j = b[b.len() - 16] as usize % n;
+        xor_slice(b, &v[j]);
+        *b = block_mix(b);
+    }
+}
+
+// rfc 7914, the cost parameter is 2^log_n
+pub fn scrypt(password: &[u8], salt: &[u8], log_n: u8, r: usize, p: usize, len: usize) -> Vec<u8> {
+    let block = 128 * r;
+    let b = pbkdf2::<Sha256, 32>(password, salt, 1, p * block);
+    let mut mixed = vec![];
+    for chk in b.chunks_exact(block) {
+        let mut x: Vec<_> = chk
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes(w.try_into().expect("invalid bs")))
+            .collect();
+        ro_mix(&mut x, 1 << log_n);
+        mixed.extend(x.into_iter().flat_map(|w| w.to_le_bytes()));
+    }
+    pbkdf2::<Sha256, 32>(password, &mixed, 1, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::Sha1;
+    use crate::encode::{from_ascii, from_hex, to_hex};
+
+    #[test]
+    fn hkdf_works() {
+        // rfc 5869, test case 1
+        let ikm = [0x0b; 22];
+        let salt = from_hex("000102030405060708090a0b0c");
+        let info = from_hex("f0f1f2f3f4f5f6f7f8f9");
+        let prk = hkdf_extract::<Sha256, 32>(&salt, &ikm);
+        let exp = "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5";
+        assert_eq!(to_hex(&prk), exp);
+        let okm = hkdf::<Sha256, 32>(&salt, &ikm, &info, 42);
+        let exp =
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865";
+        assert_eq!(to_hex(&okm), exp);
+    }
+
+    #[test]
+    fn pbkdf2_works() {
+        // rfc 6070
+        let password = from_ascii("password");
+        let salt = from_ascii("salt");
+        let dk = pbkdf2::<Sha1, 20>(&password, &salt, 1, 20);
+        assert_eq!(to_hex(&dk), "0c60c80f961f0e71f3a9b524af6012062fe037a6");
+        let dk = pbkdf2::<Sha1, 20>(&password, &salt, 4096, 20);
+        assert_eq!(to_hex(&dk), "4b007901b765489abead49d926f721d065a429c1");
+
+        // rfc 7914
+        let dk = pbkdf2::<Sha256, 32>(&from_ascii("passwd"), &salt, 1, 64);
+        let exp = "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783";
+        assert_eq!(to_hex(&dk), exp);
+    }
+
+    #[test]
+    fn scrypt_works() {
+        // rfc 7914
+        let dk = scrypt(&[], &[], 4, 1, 1, 64);
+        let exp = "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906";
+        assert_eq!(to_hex(&dk), exp);
+
+        let dk = scrypt(&from_ascii("password"), &from_ascii("NaCl"), 10, 8, 16, 64);
+        let exp = "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640";
+        assert_eq!(to_hex(&dk), exp);
+    }
+}
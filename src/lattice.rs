@@ -0,0 +1,228 @@
+use crate::math::{gcd, inv_egcd};
+use crate::poly::{div_floor, modulo, Poly};
+use num_bigint::{BigInt, BigUint};
+
+fn dot(a: &[BigInt], b: &[BigInt]) -> BigInt {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// b_k -= q b_l for the nearest integer q to mu_kl, lambda and d are the
+// integral gram-schmidt data, d[i] is the d_{i-1} of the (1 indexed) rows
+fn reduce(b: &mut [Vec<BigInt>], lambda: &mut [Vec<BigInt>], d: &[BigInt], k: usize, l: usize) {
+    let two = BigInt::from(2);
+    if lambda[k][l].magnitude() * 2u8 <= *d[l + 1].magnitude() {
+        return;
+    }
+    let q = div_floor(&(&two * &lambda[k][l] + &d[l + 1]), &(&two * &d[l + 1]));
+    let bl = b[l].clone();
+    for (x, y) in b[k].iter_mut().zip(&bl) {
+        *x -= &q * y;
+    }
+    lambda[k][l] -= &q * &d[l + 1];
+    let row = lambda[l].clone();
+    for (x, y) in lambda[k][..l].iter_mut().zip(&row) {
+        *x -= &q * y;
+    }
+}
+
+fn swap(
+    b: &mut [Vec<BigInt>],
+    lambda: &mut [Vec<BigInt>],
+    d: &mut [BigInt],
+    k: usize,
+    kmax: usize,
+) {
+    b.swap(k, k - 1);
+    let (lo, hi) = lambda.split_at_mut(k);
+    lo[k - 1][..k - 1].swap_with_slice(&mut hi[0][..k - 1]);
+    let l = lambda[k][k - 1].clone();
+    let bb = (&d[k - 1] * &d[k + 1] + &l * &l) / &d[k];
+    for row in &mut lambda[k + 1..=kmax] {
+        let t = row[k].clone();
+        row[k] = (&d[k + 1] * &row[k - 1] - &l * &t) / &d[k];
+        row[k - 1] = (&bb * &t + &l * &row[k]) / &d[k + 1];
+    }
+    d[k] = bb;
+}
+
+// rows spanning the same lattice as b without the dependent ones, by euclid's
+// algorithm down each column in turn
+fn independent(mut b: Vec<Vec<BigInt>>) -> Vec<Vec<BigInt>> {
+    let zero = BigInt::from(0);
+    let mut rows = vec![];
+    for c in 0..b.first().map_or(0, |r| r.len()) {
+        loop {
+            b.retain(|r| r.iter().any(|x| x != &zero));
+            let live: Vec<_> = (0..b.len()).filter(|&i| b[i][c] != zero).collect();
+            if live.len() < 2 {
+                if let Some(&i) = live.first() {
+                    rows.push(b.remove(i));
+                }
+                break;
+            }
+            let p = *live
+                .iter()
+                .min_by(|&&i, &&j| b[i][c].magnitude().cmp(b[j][c].magnitude()))
+                .expect("two rows at least");
+            let pivot = b[p].clone();
+            for i in live.into_iter().filter(|&i| i != p) {
+                let q = &b[i][c] / &pivot[c];
+                for (x, y) in b[i].iter_mut().zip(&pivot) {
+                    *x -= &q * y;
+                }
+            }
+        }
+    }
+    rows
+}
+
+// lll reduction with delta = 99/100, done in integers only as in cohen's
+// algorithm 2.6.7 so every division is exact. dependent rows are swapped for
+// an independent basis of the same lattice, so zero vectors are dropped
+pub fn lll(mut b: Vec<Vec<BigInt>>) -> Vec<Vec<BigInt>> {
+    let n = b.len();
+    if n < 2 {
+        return b;
+    }
+    let mut d = vec![BigInt::from(0); n + 1];
+    let mut lambda = vec![vec![BigInt::from(0); n]; n];
+    d[0] = BigInt::from(1);
+    d[1] = dot(&b[0], &b[0]);
+    let (mut k, mut kmax) = (1, 0);
+    while k < n {
+        if k > kmax {
+            kmax = k;
+            for j in 0..=k {
+                let mut u = dot(&b[k], &b[j]);
+                for i in 0..j {
+                    u = (&d[i + 1] * u - &lambda[k][i] * &lambda[j][i]) / &d[i];
+                }
+                match j < k {
+                    true => lambda[k][j] = u,
+                    false => d[k + 1] = u,
+                }
+            }
+            if d[k + 1] == BigInt::from(0) {
+                // b_k depends on the rows before it
+                return lll(independent(b));
+            }
+        }
+        reduce(&mut b, &mut lambda, &d, k, k - 1);
+        let lhs = BigInt::from(100) * &d[k + 1] * &d[k - 1];
+        let rhs = BigInt::from(99) * &d[k] * &d[k]
+            - BigInt::from(100) * &lambda[k][k - 1] * &lambda[k][k - 1];
+        if lhs < rhs {
+            swap(&mut b, &mut lambda, &mut d, k, kmax);
+            k = k.max(2) - 1;
+        } else {
+            for l in (0..k - 1).rev() {
+                reduce(&mut b, &mut lambda, &d, k, l);
+            }
+            k += 1;
+        }
+    }
+    b
+}
+
+// howgrave-graham's take on coppersmith's method, the x with |x| <= bound and
+// gcd(f(x), n) > 1, so the small roots mod n or mod a large enough factor of it.
+// the lattice holds x^j n^(m-i) f^i for j < deg f, i < m and x^j f^m for j < t
+pub fn small_roots(f: &Poly, n: &BigUint, bound: &BigUint, m: u32, t: u32) -> Vec<BigInt> {
+    let Some(d) = f.degree().filter(|d| *d > 0) else {
+        return vec![];
+    };
+    let Some(inv) = inv_egcd(&modulo(&f.coeffs()[d], n), n) else {
+        return vec![];
+    };
+    let inv = BigInt::from(inv);
+    let f = Poly::new(
+        f.coeffs()
+            .iter()
+            .map(|c| modulo(&(c * &inv), n).into())
+            .collect(),
+    );
+    let x = Poly::new(vec![0.into(), 1.into()]);
+    let mut shifts = vec![];
+    for i in 0..m {
+        let g = f.pow(i).scale(&BigInt::from(n.pow(m - i)));
+        for j in 0..d as u32 {
+            shifts.push(g.mul(&x.pow(j)));
+        }
+    }
+    for j in 0..t {
+        shifts.push(f.pow(m).mul(&x.pow(j)));
+    }
+    let dim = shifts.len();
+    let bound_i = BigInt::from(bound.clone());
+    let rows = shifts
+        .iter()
+        .map(|g| {
+            let mut row = g.scale_var(&bound_i).coeffs().to_vec();
+            row.resize(dim, 0.into());
+            row
+        })
+        .collect();
+    let mut roots = vec![];
+    for row in lll(rows).into_iter().take(2) {
+        let h = Poly::new(row).coeffs().to_vec();
+        let mut scale = BigInt::from(1);
+        let mut coeffs = vec![];
+        for c in h {
+            coeffs.push(c / &scale);
+            scale *= &bound_i;
+        }
+        for r in Poly::new(coeffs).integer_roots() {
+            let small = r.magnitude() <= bound;
+            if small && gcd(&modulo(&f.eval(&r), n), n) > 1u8.into() && !roots.contains(&r) {
+                roots.push(r);
+            }
+        }
+    }
+    roots.sort();
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(v: &[&[i64]]) -> Vec<Vec<BigInt>> {
+        v.iter()
+            .map(|r| r.iter().map(|x| BigInt::from(*x)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn lll_works() {
+        let b = lll(rows(&[&[1, 1, 1], &[-1, 0, 2], &[3, 5, 6]]));
+        assert_eq!(b, rows(&[&[0, 1, 0], &[1, 0, 1], &[-1, 0, 2]]));
+
+        // a short vector hidden by a multiple of a large one
+        let b = lll(rows(&[
+            &[1, 0, 0, 31416],
+            &[0, 1, 0, 27183],
+            &[0, 0, 1, 14142],
+            &[0, 0, 0, 100003],
+        ]));
+        let norm = |r: &Vec<BigInt>| dot(r, r);
+        assert!(norm(&b[0]) < BigInt::from(100003));
+
+        // (2, 4) is twice (1, 2) and (1, 2), (3, 7) span all of z^2
+        let b = lll(rows(&[&[2, 4], &[1, 2], &[3, 7], &[0, 0]]));
+        assert_eq!(b.len(), 2);
+        let det = &b[0][0] * &b[1][1] - &b[0][1] * &b[1][0];
+        assert_eq!(det.magnitude(), &1u8.into());
+    }
+
+    #[test]
+    fn small_roots_works() {
+        // x^2 + a x + b = 0 mod n with a root around n^(1/3)
+        let n = BigUint::from(1000000000000000003u64) * 1000000000000000009u64;
+        let r = BigInt::from(1234567890123u64);
+        let a = BigInt::from(987654321987654321u64);
+        let f = Poly::linear(r.clone()).mul(&Poly::linear(a));
+        let f = f.sub(&Poly::constant(BigInt::from(n.clone()) * 5));
+        let bound = BigUint::from(1u8) << 41;
+        assert_eq!(small_roots(&f, &n, &bound, 3, 1), vec![r]);
+    }
+}
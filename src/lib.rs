@@ -1,16 +1,26 @@
 pub mod aes;
+pub mod asn1;
 pub mod block;
+pub mod ct;
 pub mod dh;
 pub mod digest;
 pub mod dist;
 pub mod dsa;
 pub mod ec;
 pub mod encode;
+pub mod factor;
+pub mod group;
+pub mod kdf;
+pub mod lattice;
 pub mod math;
 pub mod math2;
 pub mod mersenne;
+pub mod modular;
+pub mod pkcs8;
+pub mod poly;
 pub mod prime;
 pub mod rsa;
 pub mod srp;
 pub mod stream;
+pub mod timing;
 pub mod xor;
@@ -1,5 +1,12 @@
+use crate::group::Group;
+use crate::prime::{baillie_psw, factors_up_to, primes};
 use num_bigint::BigUint;
+use rand::Rng;
+use std::collections::HashMap;
 use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 pub fn bezout(a: &BigUint, b: &BigUint, m: &BigUint) -> ((BigUint, BigUint), BigUint) {
     let (mut r, mut rn) = (a.clone(), b.clone());
@@ -19,6 +26,15 @@ pub fn bezout(a: &BigUint, b: &BigUint, m: &BigUint) -> ((BigUint, BigUint), Big
     ((s, t), r) // bezout coefs, gcd
 }
 
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while b > 0u8.into() {
+        a %= &b;
+        mem::swap(&mut a, &mut b);
+    }
+    a
+}
+
 pub fn inv_egcd(x: &BigUint, m: &BigUint) -> Option<BigUint> {
     let ((_, t), r) = bezout(m, &(x % m), m);
     if r > 1u8.into() {
@@ -44,6 +60,70 @@ pub fn nth_root(x_n: BigUint, n: u32) -> BigUint {
     prev_x
 }
 
+// a square root of a mod an odd prime p by tonelli and shanks, none for a
+// non-residue
+pub fn sqrt_mod(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    let one = BigUint::from(1u8);
+    let a = a % p;
+    if a == 0u8.into() {
+        return Some(a);
+    }
+    let half = (p - 1u8) >> 1;
+    if a.modpow(&half, p) != one {
+        return None;
+    }
+    // p - 1 = q 2^s with q odd, and z a non-residue
+    let s = (p - 1u8).trailing_zeros().expect("p is odd");
+    let q = (p - 1u8) >> s;
+    let mut z = BigUint::from(2u8);
+    while z.modpow(&half, p) == one {
+        z += 1u8;
+    }
+    let (mut m, mut c) = (s, z.modpow(&q, p));
+    let (mut t, mut r) = (a.modpow(&q, p), a.modpow(&((&q + 1u8) >> 1), p));
+    while t != one {
+        // the least i with t^(2^i) = 1
+        let (mut i, mut t2) = (0, t.clone());
+        while t2 != one {
+            t2 = &t2 * &t2 % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::from(1u8) << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * &b % p;
+    }
+    Some(r)
+}
+
+// the partial quotients of a / b
+pub fn continued_fraction(a: &BigUint, b: &BigUint) -> Vec<BigUint> {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    let mut cf = vec![];
+    while b > 0u8.into() {
+        cf.push(&a / &b);
+        a %= &b;
+        mem::swap(&mut a, &mut b);
+    }
+    cf
+}
+
+// the (numerator, denominator) of every truncation of a continued fraction
+pub fn convergents(cf: &[BigUint]) -> Vec<(BigUint, BigUint)> {
+    let (mut h, mut h_prev) = (BigUint::from(1u8), BigUint::from(0u8));
+    let (mut k, mut k_prev) = (BigUint::from(0u8), BigUint::from(1u8));
+    let mut res = vec![];
+    for a in cf {
+        h_prev = a * &h + h_prev;
+        k_prev = a * &k + k_prev;
+        mem::swap(&mut h, &mut h_prev);
+        mem::swap(&mut k, &mut k_prev);
+        res.push((h.clone(), k.clone()));
+    }
+    res
+}
+
 pub fn crt(v: &[(BigUint, BigUint)]) -> (BigUint, BigUint) {
     let prod: BigUint = v.iter().map(|(_, n)| n).product();
     let s: BigUint = v
@@ -58,39 +138,47 @@ pub fn crt(v: &[(BigUint, BigUint)]) -> (BigUint, BigUint) {
     (s % &prod, prod)
 }
 
-pub fn disc_log_incr(p: &BigUint, ul: &BigUint, g: &BigUint, gx: &BigUint) -> Option<BigUint> {
+pub fn disc_log_incr<G: Group>(
+    group: &G,
+    ul: &BigUint,
+    g: &G::Element,
+    gx: &G::Element,
+) -> Option<BigUint> {
     let mut x = BigUint::from(0u8);
-    let mut gx_ = BigUint::from(1u8);
+    let mut gx_ = group.identity();
     while &x < ul {
         if &gx_ == gx {
             return Some(x);
         }
-        gx_ = (gx_ * g) % p;
+        gx_ = group.op(&gx_, g);
         x += 1u8;
     }
     None
 }
 
-fn pr_step(
-    (mut x, mut eg, mut ea): (BigUint, BigUint, BigUint),
-    p: &BigUint,
+// x g^a h^b steps to x g, x h or x^2 depending on the hash of x
+fn pr_step<G: Group>(
+    (x, a, b): (G::Element, BigUint, BigUint),
+    group: &G,
     q: &BigUint,
-    g: &BigUint,
-) -> (BigUint, BigUint, BigUint) {
-    if x.bit(0) {
-        x *= g;
-        eg += 1u8;
-    } else {
-        x *= x.clone();
-        eg *= 2u8;
-        ea *= 2u8;
+    g: &G::Element,
+    h: &G::Element,
+) -> (G::Element, BigUint, BigUint) {
+    match group.hash(&x) % 3 {
+        0 => (group.op(&x, g), (a + 1u8) % q, b),
+        1 => (group.op(&x, h), a, (b + 1u8) % q),
+        _ => (group.op(&x, &x), a * 2u8 % q, b * 2u8 % q),
     }
-    (x % p, eg % q, ea % q)
 }
 
-pub fn pollard_rho(p: &BigUint, q: &BigUint, g: &BigUint, gx: &BigUint) -> Option<BigUint> {
+pub fn pollard_rho<G: Group>(
+    group: &G,
+    q: &BigUint,
+    g: &G::Element,
+    gx: &G::Element,
+) -> Option<BigUint> {
     let init = (gx.clone(), BigUint::from(0u8), BigUint::from(1u8));
-    let f = |tup| pr_step(tup, p, q, g);
+    let f = |tup| pr_step(tup, group, q, g, gx);
     let mut t = f(init);
     let mut h = f(t.clone());
     while t.0 != h.0 {
@@ -100,28 +188,28 @@ pub fn pollard_rho(p: &BigUint, q: &BigUint, g: &BigUint, gx: &BigUint) -> Optio
     div(&(t.1 + q - h.1), &(h.2 + q - t.2), q)
 }
 
-pub fn pollard_lambda(
-    p: &BigUint,
-    g: &BigUint,
-    ge: &BigUint,
+pub fn pollard_lambda<G: Group>(
+    group: &G,
+    g: &G::Element,
+    ge: &G::Element,
     ll: &BigUint,
     ul: &BigUint,
     k: u8,
     c: u8,
 ) -> Option<BigUint> {
-    let mut x = g.modpow(ul, p);
+    let mut x = group.exp(g, ul);
     let mut d = BigUint::from(0u8);
     assert!(k <= 120, "k must be smaller than ~120 to fit into u128");
-    let get_i = |x: &BigUint| (x % k).to_u64_digits().pop().unwrap_or(0) as usize;
+    let get_i = |x: &G::Element| (group.hash(x) % k as u64) as usize;
     let f = |i: u8| 1u128 << i;
     // mean of f * c
     let n = ((1u128 << k) - 1) * c as u128 / k as u128;
 
     let fs: Vec<_> = (0..k).map(f).collect();
-    let gs: Vec<_> = fs.iter().map(|e| g.modpow(&(*e).into(), p)).collect();
+    let gs: Vec<_> = fs.iter().map(|e| group.exp(g, &(*e).into())).collect();
     for _ in 0..n {
         let i = get_i(&x);
-        x = (x * &gs[i]) % p;
+        x = group.op(&x, &gs[i]);
         d += fs[i];
     }
     // x = xn = x0 g^d = g^b g^d = g ^ (b+d)
@@ -130,7 +218,7 @@ pub fn pollard_lambda(
     let clim = ul - ll + &d;
     while c < clim {
         let i = get_i(&y);
-        y = (y * &gs[i]) % p;
+        y = group.op(&y, &gs[i]);
         c += fs[i];
         if y == x {
             return Some(ul + d - c);
@@ -139,10 +227,332 @@ pub fn pollard_lambda(
     None
 }
 
+// van oorschot and wiener's parallel kangaroos for the x in [lower, upper] with
+// g^x = h. every thread runs a tame kangaroo from the middle of the interval
+// and a wild one from h, and they leave traps at distinguished points, the
+// elements with their low bits zero, in a table they share. a tame and a wild
+// kangaroo meeting there give x. the jumps and the share of distinguished points
+// follow from the interval width, and progress gets the jumps made so far with
+// roughly how many the search should take
+pub fn kangaroo<G: Group + Sync>(
+    group: &G,
+    g: &G::Element,
+    h: &G::Element,
+    lower: &BigUint,
+    upper: &BigUint,
+    threads: usize,
+    progress: impl Fn(u64, u64) + Sync,
+) -> Option<BigUint> {
+    let width = upper - lower;
+    let threads = threads.max(1);
+    let herd = 2.0 * threads as f64;
+    let root = (width.bits() as f64 / 2.0).exp2();
+    // jumps of 2^i for i < k, whose mean is about herd sqrt(width) / 4
+    let mean = (herd * root / 4.0).max(1.0);
+    let k = (1..127)
+        .find(|k| ((1u128 << k) - 1) as f64 / *k as f64 >= mean)
+        .unwrap_or(127);
+    let jumps: Vec<u128> = (0..k).map(|i| 1 << i).collect();
+    let steps: Vec<_> = jumps.iter().map(|j| group.exp(g, &(*j).into())).collect();
+    // about one distinguished point every sqrt(width) / (8 herd) jumps
+    let dp_bits = (root / herd / 8.0).log2().clamp(0.0, 32.0) as u32;
+    let expected = (2.0 * root) as u64 + ((herd as u64) << dp_bits);
+    let spacing = (mean / herd).max(2.0) as u128;
+    let mid = lower + (&width >> 1);
+
+    let traps = Mutex::new(HashMap::new());
+    let found = Mutex::new(None);
+    let done = AtomicBool::new(false);
+    let total = AtomicU64::new(0);
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (steps, jumps, mid) = (&steps, &jumps, &mid);
+            let (traps, found, done, total, progress) = (&traps, &found, &done, &total, &progress);
+            s.spawn(move || {
+                let mut rng = rand::thread_rng();
+                let shift = t as u128 * spacing;
+                // the element, the distance travelled, where it started and
+                // whether it is tame: a tame one is at g^(start + travel) and a
+                // wild one at h g^(start + travel)
+                let mut roos = [
+                    (group.exp(g, &(mid + shift)), 0u128, mid + shift, true),
+                    (
+                        group.op(h, &group.exp(g, &shift.into())),
+                        0,
+                        shift.into(),
+                        false,
+                    ),
+                ];
+                let mut made = 0;
+                while !done.load(Ordering::Relaxed) {
+                    for (x, travel, start, tame) in roos.iter_mut() {
+                        let i = (group.hash(x).wrapping_mul(0x9e3779b97f4a7c15) >> 32) as usize % k;
+                        *x = group.op(x, &steps[i]);
+                        *travel += jumps[i];
+                        if group.hash(x) & ((1 << dp_bits) - 1) != 0 {
+                            continue;
+                        }
+                        let log = &*start + *travel;
+                        let mut traps = traps.lock().expect("a kangaroo thread panicked");
+                        let Some((other_tame, other_log)) = traps.get(&*x) else {
+                            traps.insert(x.clone(), (*tame, log));
+                            continue;
+                        };
+                        if other_tame != tame {
+                            let (t, w) = match *tame {
+                                true => (&log, other_log),
+                                false => (other_log, &log),
+                            };
+                            if t >= w {
+                                let x = t - w;
+                                if &x >= lower && &x <= upper && group.exp(g, &x) == *h {
+                                    *found.lock().expect("a kangaroo thread panicked") = Some(x);
+                                    done.store(true, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                        // both walk the same path from here, so push this one off it
+                        let r = rng.gen_range(1..spacing);
+                        *x = group.op(x, &group.exp(g, &r.into()));
+                        *travel += r;
+                    }
+                    made += 2;
+                    if made == 1 << 14 {
+                        let jumps = total.fetch_add(made, Ordering::Relaxed) + made;
+                        progress(jumps, expected);
+                        if jumps > 16 * expected {
+                            done.store(true, Ordering::Relaxed);
+                        }
+                        made = 0;
+                    }
+                }
+            });
+        }
+    });
+    found.into_inner().expect("a kangaroo thread panicked")
+}
+
+// baby-step giant-step, the x < n with g^x = h, in sqrt(n) steps and memory
+pub fn bsgs<G: Group>(group: &G, g: &G::Element, h: &G::Element, n: &BigUint) -> Option<BigUint> {
+    let m = nth_root(n.clone(), 2) + 1u8;
+    let mut baby = HashMap::new();
+    let mut x = group.identity();
+    let mut j = BigUint::from(0u8);
+    while j < m {
+        baby.entry(x.clone()).or_insert(j.clone());
+        x = group.op(&x, g);
+        j += 1u8;
+    }
+    // g^-m
+    let giant = group.inverse(&group.exp(g, &m));
+    let mut y = h.clone();
+    let mut i = BigUint::from(0u8);
+    while i < m {
+        if let Some(j) = baby.get(&y) {
+            let x = &i * &m + j;
+            return (&x < n).then_some(x);
+        }
+        y = group.op(&y, &giant);
+        i += 1u8;
+    }
+    None
+}
+
+// the exponents of x over the factor base, none if it does not split over it
+fn smooth(x: &BigUint, base: &[u64]) -> Option<Vec<u32>> {
+    let zero = BigUint::from(0u8);
+    let mut x = x.clone();
+    if x == zero {
+        return None;
+    }
+    let mut exps = vec![0; base.len()];
+    for (e, p) in exps.iter_mut().zip(base) {
+        while &x % p == zero {
+            x /= *p;
+            *e += 1;
+        }
+    }
+    (x == 1u8.into()).then_some(exps)
+}
+
+// the unknowns pinned down by linear equations mod a prime q, given as rows of
+// coefficients with the constant last
+fn solve_mod(mut rows: Vec<Vec<BigUint>>, q: &BigUint) -> Vec<Option<BigUint>> {
+    let zero = BigUint::from(0u8);
+    let cols = rows.first().map_or(0, |r| r.len() - 1);
+    for row in rows.iter_mut() {
+        row.iter_mut().for_each(|x| *x %= q);
+    }
+    let mut pivots = vec![];
+    for j in 0..cols {
+        let r = pivots.len();
+        let Some(i) = (r..rows.len()).find(|i| rows[*i][j] != zero) else {
+            continue;
+        };
+        rows.swap(r, i);
+        let inv = inv_egcd(&rows[r][j], q).expect("q is a prime");
+        let pivot: Vec<_> = rows[r].iter().map(|x| x * &inv % q).collect();
+        for row in rows.iter_mut() {
+            let f = row[j].clone();
+            if f != zero {
+                for (x, y) in row.iter_mut().zip(&pivot) {
+                    *x = (&*x + q - y * &f % q) % q;
+                }
+            }
+        }
+        rows[r] = pivot;
+        pivots.push(j);
+    }
+    let mut logs = vec![None; cols];
+    for (row, &j) in rows.iter().zip(&pivots) {
+        if (0..cols).all(|c| c == j || row[c] == zero) {
+            logs[j] = Some(row[cols].clone());
+        }
+    }
+    logs
+}
+
+// the cost of index calculus for a prime p, l_p[1/2, sqrt 2], in bits
+pub fn index_calculus_bits(p: &BigUint) -> f64 {
+    let ln_p = p.bits() as f64 * std::f64::consts::LN_2;
+    (2.0 * ln_p * ln_p.ln()).sqrt() / std::f64::consts::LN_2
+}
+
+// index calculus for log h mod q to the base g in z_p^*, where q is a prime that
+// divides the order of g and c = (p - 1) / q is prime to it. the relations
+// g^k = prod p_i^e_i give the logs of p_i^c, then a smooth h g^k gives log h
+pub fn index_calculus(p: &BigUint, g: &BigUint, h: &BigUint, q: &BigUint) -> Option<BigUint> {
+    let c = (p - 1u8) / q;
+    let c_inv = inv_egcd(&(&c % q), q)?;
+    let ln_p = p.bits() as f64 * std::f64::consts::LN_2;
+    let bound = (0.7 * (ln_p * ln_p.ln()).sqrt()).exp().clamp(32.0, 65536.0);
+    let base: Vec<_> = primes(bound as u64).collect();
+    let mut rows = vec![];
+    let (mut y, mut k) = (BigUint::from(1u8), BigUint::from(0u8));
+    while rows.len() < base.len() + 16 {
+        y = y * g % p;
+        k += 1u8;
+        if &k > q {
+            return None;
+        }
+        if let Some(exps) = smooth(&y, &base) {
+            let mut row: Vec<_> = exps.into_iter().map(BigUint::from).collect();
+            row.push(&k * &c);
+            rows.push(row);
+        }
+    }
+    let logs = solve_mod(rows, q);
+    let (mut y, mut k) = (h % p, BigUint::from(0u8));
+    while &k < q {
+        if let Some(exps) = smooth(&y, &base) {
+            let terms = exps.iter().zip(&logs).map(|(e, l)| match e {
+                0 => Some(BigUint::from(0u8)),
+                _ => l.as_ref().map(|l| l * e),
+            });
+            // c (x + k) = sum e_i log p_i^c mod q
+            if let Some(s) = terms.sum::<Option<BigUint>>() {
+                return Some((s * &c_inv % q + q - &k % q) % q);
+            }
+        }
+        y = y * g % p;
+        k += 1u8;
+    }
+    None
+}
+
+// log h to a base g of prime order q, by baby-step giant-step while the table
+// stays small and by pollard's rho past that
+pub fn prime_order_log<G: Group>(
+    group: &G,
+    g: &G::Element,
+    h: &G::Element,
+    q: &BigUint,
+) -> Option<BigUint> {
+    if *h == group.identity() {
+        return Some(0u8.into());
+    }
+    let x = match q.bits() {
+        0..=40 => bsgs(group, g, h, q),
+        _ => pollard_rho(group, q, g, h),
+    };
+    x.filter(|x| group.exp(g, x) == *h)
+}
+
+// pohlig-hellman: log h to the base g from the logs in each prime power part of
+// order, a factored multiple of the order of g
+pub fn pohlig_hellman<G: Group>(
+    group: &G,
+    g: &G::Element,
+    h: &G::Element,
+    order: &[(BigUint, u32)],
+) -> Option<BigUint> {
+    let n: BigUint = order.iter().map(|(q, e)| q.pow(*e)).product();
+    let mut residues = vec![];
+    let one = group.identity();
+    for (q, e) in order {
+        let cofactor = &n / q.pow(*e);
+        let (g0, h0) = (group.exp(g, &cofactor), group.exp(h, &cofactor));
+        // the order of g0 is q^e for a smaller e when g does not generate it all
+        let mut e = *e;
+        while e > 0 && group.exp(&g0, &q.pow(e - 1)) == one {
+            e -= 1;
+        }
+        if e == 0 {
+            match h0 == one {
+                true => continue,
+                false => return None,
+            }
+        }
+        let g0_inv = group.inverse(&g0);
+        // of order q, the base for each digit of x in base q
+        let gamma = group.exp(&g0, &q.pow(e - 1));
+        let mut x = BigUint::from(0u8);
+        for k in 0..e {
+            let hk = group.exp(&group.op(&group.exp(&g0_inv, &x), &h0), &q.pow(e - 1 - k));
+            x += group.prime_order_log(&gamma, &hk, q)? * q.pow(k);
+        }
+        residues.push((x, q.pow(e)));
+    }
+    Some(crt(&residues).0)
+}
+
+// the x with g^x = h, reduced mod the order of g. the order of the group is
+// split by the sieve up to 2^20 and must leave at most one larger prime, pass
+// the factors to discrete_log_factored otherwise
+pub fn discrete_log<G: Group>(g: &G::Element, h: &G::Element, group: &G) -> Option<BigUint> {
+    let n = group.order();
+    let mut rest = n.clone();
+    let mut order = vec![];
+    for (q, e) in factors_up_to(&n, 1 << 20) {
+        rest /= BigUint::from(q).pow(e);
+        order.push((q.into(), e));
+    }
+    if rest > 1u8.into() {
+        if !baillie_psw(&rest) {
+            return None;
+        }
+        order.push((rest, 1));
+    }
+    discrete_log_factored(g, h, group, &order)
+}
+
+// discrete_log for a caller who knows the factors of the group order, or of a
+// multiple of the order of g
+pub fn discrete_log_factored<G: Group>(
+    g: &G::Element,
+    h: &G::Element,
+    group: &G,
+    order: &[(BigUint, u32)],
+) -> Option<BigUint> {
+    let x = pohlig_hellman(group, g, h, order)?;
+    (group.exp(g, &x) == *h).then_some(x)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::prime::{mr_prime, pq};
+    use crate::group::ModGroup;
+    use crate::prime::{baillie_psw, mr_prime, pq};
     use num_bigint::RandBigInt;
 
     #[test]
@@ -189,6 +599,38 @@ mod tests {
         assert_eq!(a * b % p, one);
     }
 
+    #[test]
+    fn gcd_works() {
+        let g = |a: u32, b: u32| gcd(&a.into(), &b.into());
+        assert_eq!(g(12, 18), 6u8.into());
+        assert_eq!(g(17, 5), 1u8.into());
+        assert_eq!(g(0, 7), 7u8.into());
+        assert_eq!(g(7, 0), 7u8.into());
+    }
+
+    #[test]
+    fn sqrt_mod_works() {
+        let mut rng = rand::thread_rng();
+        // 2^32 - 2^2 + 1 = 1 mod 2^2 and 2^64 - 2^32 + 1 = 1 mod 2^32
+        for p in [4294967291u64, 4294967197, 18446744069414584321] {
+            let p = BigUint::from(p);
+            let x = rng.gen_biguint_below(&p);
+            let r = sqrt_mod(&(&x * &x), &p).expect("x^2 is a square");
+            assert!(r == x || r == &p - &x);
+        }
+        assert_eq!(sqrt_mod(&3u8.into(), &7u8.into()), None);
+    }
+
+    #[test]
+    fn continued_fraction_works() {
+        let big = |v: &[u16]| -> Vec<BigUint> { v.iter().map(|x| (*x).into()).collect() };
+        let cf = continued_fraction(&649u16.into(), &200u8.into());
+        assert_eq!(cf, big(&[3, 4, 12, 4]));
+        let (h, k): (Vec<_>, Vec<_>) = convergents(&cf).into_iter().unzip();
+        assert_eq!(h, big(&[3, 13, 159, 649]));
+        assert_eq!(k, big(&[1, 4, 49, 200]));
+    }
+
     #[test]
     fn nth_root_works() {
         let x = BigUint::from(123usize);
@@ -216,7 +658,11 @@ mod tests {
             .modpow(&r, &p);
         let x = &rng.gen_biguint_range(&0u8.into(), &(q - 1u8));
         let gx = &g.modpow(x, p);
-        let x2 = &pollard_rho(p, q, g, gx).expect("not invertible");
+        let group = &ModGroup {
+            p: p.clone(),
+            order: q.clone(),
+        };
+        let x2 = &pollard_rho(group, q, g, gx).expect("not invertible");
         assert_eq!(x2, x);
     }
 
@@ -231,8 +677,119 @@ mod tests {
         let y = &g.modpow(x, p);
         let k = up / 2;
         let c = 4;
-        let x2 = &pollard_lambda(p, g, y, ll, ul, k as u8, c).expect("did not find x");
+        let group = &ModGroup {
+            p: p.clone(),
+            order: p - 1u8,
+        };
+        let x2 = &pollard_lambda(group, g, y, ll, ul, k as u8, c).expect("did not find x");
         assert_eq!(x, x2);
         assert_eq!(&g.modpow(&x2, p), y);
     }
+
+    #[test]
+    fn kangaroo_works() {
+        let mut rng = rand::thread_rng();
+        let (p, g) = &pq(256, 128, 5, &mut rng);
+        let lower = &(BigUint::from(1u8) << 50);
+        let upper = &(lower + (1u64 << 36));
+        let x = rng.gen_biguint_range(lower, upper);
+        let y = &g.modpow(&x, p);
+        let reports = AtomicU64::new(0);
+        let progress = |_, _| {
+            reports.fetch_add(1, Ordering::Relaxed);
+        };
+        let group = &ModGroup {
+            p: p.clone(),
+            order: p - 1u8,
+        };
+        let x2 = kangaroo(group, g, y, lower, upper, 4, progress).expect("did not find x");
+        assert_eq!(x2, x);
+        assert!(reports.into_inner() > 0);
+    }
+
+    #[test]
+    fn bsgs_works() {
+        let mut rng = rand::thread_rng();
+        let p = mr_prime(128, 5, &mut rng);
+        let g = rng.gen_biguint_range(&2u8.into(), &p);
+        let n = BigUint::from(1u32 << 24);
+        let x = rng.gen_biguint_below(&n);
+        let group = &ModGroup { order: &p - 1u8, p };
+        let x2 = bsgs(group, &g, &group.exp(&g, &x), &n).expect("x is below n");
+        assert_eq!(group.exp(&g, &x2), group.exp(&g, &x));
+        assert!(x2 <= x);
+    }
+
+    #[test]
+    fn index_calculus_works() {
+        let mut rng = rand::thread_rng();
+        let (p, q) = &pq(36, 28, 5, &mut rng);
+        let g = &rng
+            .gen_biguint_range(&2u8.into(), &(p - 1u8))
+            .modpow(&((p - 1u8) / q), p);
+        let x = rng.gen_biguint_below(q);
+        let x2 = index_calculus(p, g, &g.modpow(&x, p), q).expect("x not found");
+        assert_eq!(x2, x);
+    }
+
+    #[test]
+    fn discrete_log_works() {
+        let mut rng = rand::thread_rng();
+        // p - 1 smooth but for a 40 bit prime, with 2^3 and 3^5 among the factors
+        let (p, factors) = loop {
+            let mut order = vec![
+                (2u8.into(), 3),
+                (3u8.into(), 5),
+                (mr_prime(40, 5, &mut rng), 1),
+            ];
+            while order.len() < 7 {
+                let q = mr_prime(16, 5, &mut rng);
+                if !order.iter().any(|(f, _)| *f == q) {
+                    order.push((q, 1));
+                }
+            }
+            let n: BigUint = order
+                .iter()
+                .map(|(q, e): &(BigUint, u32)| q.pow(*e))
+                .product();
+            if baillie_psw(&(&n + 1u8)) {
+                break (n + 1u8, order);
+            }
+        };
+        let group = ModGroup { order: &p - 1u8, p };
+        // a non-square, so that g is not a power of g^2
+        let half = (&group.p - 1u8) >> 1;
+        let g = loop {
+            let g = rng.gen_biguint_range(&2u8.into(), &group.p);
+            if g.modpow(&half, &group.p) != 1u8.into() {
+                break g;
+            }
+        };
+        let x = rng.gen_biguint_below(&group.p);
+        let h = g.modpow(&x, &group.p);
+        let x2 = discrete_log(&g, &h, &group).expect("h is a power of g");
+        assert_eq!(g.modpow(&x2, &group.p), h);
+        assert_eq!(pohlig_hellman(&group, &g, &h, &factors), Some(x2));
+
+        // not in the subgroup of g
+        let g2 = g.modpow(&2u8.into(), &group.p);
+        let h2 = g2.modpow(&x, &group.p) * &g % &group.p;
+        assert_eq!(discrete_log(&g2, &h2, &group), None);
+
+        // two factors past the sieve, which only the caller knows
+        let (p, factors) = loop {
+            let (q1, q2) = (mr_prime(24, 5, &mut rng), mr_prime(24, 5, &mut rng));
+            let n = 2u8 * &q1 * &q2;
+            if q1 != q2 && baillie_psw(&(&n + 1u8)) {
+                break (n + 1u8, vec![(2u8.into(), 1), (q1, 1), (q2, 1)]);
+            }
+        };
+        let group = ModGroup { order: &p - 1u8, p };
+        let g = BigUint::from(3u8);
+        let x = rng.gen_biguint_below(&group.p);
+        let h = g.modpow(&x, &group.p);
+        assert_eq!(discrete_log(&g, &h, &group), None);
+        let x2 = discrete_log_factored(&g, &h, &group, &factors).expect("h is a power of g");
+        assert_eq!(g.modpow(&x2, &group.p), h);
+    }
 }
@@ -0,0 +1,327 @@
+use num_bigint::BigUint;
+
+// a number mod n in montgomery form, x 2^(64 k) mod n in k limbs of 64 bits
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Residue(Vec<u64>);
+
+impl Residue {
+    pub fn low(&self) -> u64 {
+        self.0[0]
+    }
+}
+
+// arithmetic mod an odd n with montgomery multiplication on 64 bit limbs, so
+// products are reduced with multiplications and shifts instead of a division
+#[derive(Clone, Debug)]
+pub struct ModContext {
+    n: BigUint,
+    limbs: Vec<u64>,
+    // -1 / n mod 2^64
+    n0: u64,
+    one: Residue,
+    r2: Residue,
+}
+
+fn to_limbs(x: &BigUint, k: usize) -> Vec<u64> {
+    let mut v: Vec<u64> = x.iter_u64_digits().collect();
+    v.resize(k, 0);
+    v
+}
+
+fn from_limbs(v: &[u64]) -> BigUint {
+    let digits: Vec<u32> = v
+        .iter()
+        .flat_map(|l| [*l as u32, (l >> 32) as u32])
+        .collect();
+    BigUint::from_slice(&digits)
+}
+
+// the w bits of e from bit lo up
+fn digit(e: &BigUint, lo: u64, w: u32) -> usize {
+    (0..w).fold(0, |d, i| d | (e.bit(lo + i as u64) as usize) << i)
+}
+
+impl ModContext {
+    pub fn new(n: &BigUint) -> Option<Self> {
+        if !n.bit(0) || n == &1u8.into() {
+            return None;
+        }
+        let k = n.iter_u64_digits().len();
+        let limbs = to_limbs(n, k);
+        // newton's iteration doubles the correct low bits of 1 / n each time
+        let mut inv = 1u64;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+        let r = BigUint::from(1u8) << (64 * k);
+        Some(ModContext {
+            one: Residue(to_limbs(&(&r % n), k)),
+            r2: Residue(to_limbs(&(&r * &r % n), k)),
+            n: n.clone(),
+            limbs,
+            n0: inv.wrapping_neg(),
+        })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    pub fn one(&self) -> Residue {
+        self.one.clone()
+    }
+
+    pub fn residue(&self, x: &BigUint) -> Residue {
+        let x = Residue(to_limbs(&(x % &self.n), self.limbs.len()));
+        self.mul(&x, &self.r2)
+    }
+
+    pub fn value(&self, a: &Residue) -> BigUint {
+        let mut one = vec![0; self.limbs.len()];
+        one[0] = 1;
+        from_limbs(&self.mul(a, &Residue(one)).0)
+    }
+
+    // a b / 2^(64 k) mod n, interleaving the multiplication with the reduction
+    // one limb of b at a time: after adding a b_i, the multiple of n that
+    // clears the low limb goes in too in the same pass and t shifts down a
+    // limb. t is scratch of k + 1 limbs and the result is left in t[..k]
+    fn mul_into(&self, t: &mut [u64], a: &[u64], b: &[u64]) {
+        let k = self.limbs.len();
+        // the same length for all so the bounds checks go
+        let (n, a, t) = (&self.limbs[..k], &a[..k], &mut t[..=k]);
+        t.fill(0);
+        for &bi in b {
+            let s = t[0] as u128 + a[0] as u128 * bi as u128;
+            let m = (s as u64).wrapping_mul(self.n0);
+            let (mut c1, mut c2) = (s >> 64, (s as u64 as u128 + m as u128 * n[0] as u128) >> 64);
+            for j in 1..k {
+                let s = t[j] as u128 + a[j] as u128 * bi as u128 + c1;
+                let s2 = s as u64 as u128 + m as u128 * n[j] as u128 + c2;
+                (c1, c2) = (s >> 64, s2 >> 64);
+                t[j - 1] = s2 as u64;
+            }
+            let s = t[k] as u128 + c1 + c2;
+            t[k - 1] = s as u64;
+            t[k] = (s >> 64) as u64;
+        }
+        // t < 2n here, so one subtraction at most
+        let below = t[..k]
+            .iter()
+            .zip(n)
+            .rev()
+            .find(|(tj, nj)| tj != nj)
+            .is_some_and(|(tj, nj)| tj < nj);
+        if t[k] != 0 || !below {
+            let mut borrow = false;
+            for (tj, &nj) in t.iter_mut().zip(n) {
+                let (d, b1) = tj.overflowing_sub(nj);
+                let (d, b2) = d.overflowing_sub(borrow as u64);
+                *tj = d;
+                borrow = b1 || b2;
+            }
+        }
+    }
+
+    fn scratch(&self) -> Vec<u64> {
+        vec![0; self.limbs.len() + 1]
+    }
+
+    pub fn mul(&self, a: &Residue, b: &Residue) -> Residue {
+        let k = self.limbs.len();
+        let mut t = self.scratch();
+        self.mul_into(&mut t, &a.0, &b.0);
+        Residue(t[..k].to_vec())
+    }
+
+    // x = x b and x = x^2 in place, so the loops in pow allocate nothing
+    fn mul_assign(&self, t: &mut [u64], x: &mut Residue, b: &Residue) {
+        let k = self.limbs.len();
+        self.mul_into(t, &x.0, &b.0);
+        x.0.copy_from_slice(&t[..k]);
+    }
+
+    fn square(&self, t: &mut [u64], x: &mut Residue) {
+        let k = self.limbs.len();
+        self.mul_into(t, &x.0, &x.0);
+        x.0.copy_from_slice(&t[..k]);
+    }
+
+    // a^e with a table of a^0 .. a^(2^w - 1), one multiplication per w bits of e
+    pub fn pow_fixed(&self, a: &Residue, e: &BigUint, w: u32) -> Residue {
+        let mut table = vec![self.one()];
+        for i in 1..1 << w {
+            table.push(self.mul(&table[i - 1], a));
+        }
+        let windows = e.bits().div_ceil(w as u64);
+        let (mut x, t) = (self.one(), &mut self.scratch());
+        for i in (0..windows).rev() {
+            if i + 1 < windows {
+                for _ in 0..w {
+                    self.square(t, &mut x);
+                }
+            }
+            let d = digit(e, i * w as u64, w);
+            if d != 0 {
+                self.mul_assign(t, &mut x, &table[d]);
+            }
+        }
+        x
+    }
+
+    // a^e with a table of the odd powers a, a^3 .. a^(2^w - 1) only, the windows
+    // start and end on a set bit and runs of zeros cost just squarings
+    pub fn pow_sliding(&self, a: &Residue, e: &BigUint, w: u32) -> Residue {
+        let a2 = self.mul(a, a);
+        let mut odd = vec![a.clone()];
+        for i in 1..1 << (w - 1) {
+            odd.push(self.mul(&odd[i - 1], &a2));
+        }
+        let (mut x, t) = (self.one(), &mut self.scratch());
+        let mut i = e.bits() as i64 - 1;
+        while i >= 0 {
+            if !e.bit(i as u64) {
+                self.square(t, &mut x);
+                i -= 1;
+                continue;
+            }
+            let mut j = (i - w as i64 + 1).max(0);
+            while !e.bit(j as u64) {
+                j += 1;
+            }
+            let mut d = 0;
+            for b in (j..=i).rev() {
+                self.square(t, &mut x);
+                d = d << 1 | e.bit(b as u64) as usize;
+            }
+            self.mul_assign(t, &mut x, &odd[d >> 1]);
+            i = j - 1;
+        }
+        x
+    }
+
+    pub fn pow(&self, a: &Residue, e: &BigUint) -> Residue {
+        let w = match e.bits() {
+            0..=64 => 3,
+            65..=256 => 4,
+            257..=1024 => 5,
+            _ => 6,
+        };
+        self.pow_sliding(a, e, w)
+    }
+
+    // the product of the a^e by straus's method: the squarings are shared, and
+    // bases in groups of 4 / w get a table of all their products a1^d1 a2^d2 ..
+    // with the d below 2^w, so each group costs one multiplication per window.
+    // two bases with w = 2 is shamir's trick with two bits at a time
+    pub fn multi_pow(&self, terms: &[(&Residue, &BigUint)]) -> Residue {
+        let w = (4 / terms.len().max(1)).max(1) as u32;
+        let m = 4 / w as usize;
+        let tables: Vec<_> = terms
+            .chunks(m)
+            .map(|group| {
+                let mut table = vec![self.one()];
+                for d in 1..1 << (w as usize * group.len()) {
+                    // take off the lowest nonzero digit
+                    let j = (d as u32).trailing_zeros() / w;
+                    table.push(self.mul(&table[d - (1 << (j * w))], group[j as usize].0));
+                }
+                table
+            })
+            .collect();
+        let bits = terms.iter().map(|(_, e)| e.bits()).max().unwrap_or(0);
+        let windows = bits.div_ceil(w as u64);
+        let (mut x, t) = (self.one(), &mut self.scratch());
+        for i in (0..windows).rev() {
+            if i + 1 < windows {
+                for _ in 0..w {
+                    self.square(t, &mut x);
+                }
+            }
+            for (group, table) in terms.chunks(m).zip(&tables) {
+                let d = group.iter().enumerate().fold(0, |d, (j, (_, e))| {
+                    d | digit(e, i * w as u64, w) << (j * w as usize)
+                });
+                if d != 0 {
+                    self.mul_assign(t, &mut x, &table[d]);
+                }
+            }
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    fn moduli(rng: &mut impl rand::Rng) -> Vec<BigUint> {
+        let mut ns: Vec<BigUint> = [3u64, u64::MAX, 0xffff_ffff_0000_0001]
+            .iter()
+            .map(|&n| n.into())
+            .collect();
+        ns.push((BigUint::from(1u8) << 128) - 1u8);
+        for bits in [65, 127, 128, 521, 1024] {
+            ns.push(rng.gen_biguint(bits) | BigUint::from(1u8) | BigUint::from(1u8) << (bits - 1));
+        }
+        ns
+    }
+
+    #[test]
+    fn mul_works() {
+        let mut rng = rand::thread_rng();
+        for n in moduli(&mut rng) {
+            let ctx = ModContext::new(&n).expect("n is odd");
+            for a in [BigUint::from(0u8), &n - 1u8, rng.gen_biguint_below(&n)] {
+                let b = rng.gen_biguint_below(&n);
+                let ab = ctx.mul(&ctx.residue(&a), &ctx.residue(&b));
+                assert_eq!(ctx.value(&ab), &a * &b % &n);
+            }
+            assert_eq!(
+                ctx.value(&ctx.residue(&(&n + 5u8))),
+                BigUint::from(5u8) % &n
+            );
+            assert_eq!(ctx.value(&ctx.one()), 1u8.into());
+        }
+        assert!(ModContext::new(&10u8.into()).is_none());
+        assert!(ModContext::new(&1u8.into()).is_none());
+    }
+
+    #[test]
+    fn pow_works() {
+        let mut rng = rand::thread_rng();
+        for n in moduli(&mut rng) {
+            let ctx = ModContext::new(&n).expect("n is odd");
+            let a = rng.gen_biguint_below(&n);
+            let ra = ctx.residue(&a);
+            for e in [0u8.into(), 1u8.into(), rng.gen_biguint(300), n.clone()] {
+                let ae = a.modpow(&e, &n);
+                for w in 1..7 {
+                    assert_eq!(ctx.value(&ctx.pow_fixed(&ra, &e, w)), ae);
+                    assert_eq!(ctx.value(&ctx.pow_sliding(&ra, &e, w)), ae);
+                }
+                assert_eq!(ctx.value(&ctx.pow(&ra, &e)), ae);
+            }
+        }
+    }
+
+    #[test]
+    fn multi_pow_works() {
+        let mut rng = rand::thread_rng();
+        for n in moduli(&mut rng) {
+            let ctx = ModContext::new(&n).expect("n is odd");
+            for m in 0..7 {
+                let terms: Vec<_> = (0..m)
+                    .map(|i| (rng.gen_biguint_below(&n), rng.gen_biguint(40 * i + 1)))
+                    .collect();
+                let expected = terms
+                    .iter()
+                    .fold(BigUint::from(1u8), |x, (a, e)| x * a.modpow(e, &n) % &n);
+                let rs: Vec<_> = terms.iter().map(|(a, _)| ctx.residue(a)).collect();
+                let args: Vec<_> = rs.iter().zip(&terms).map(|(r, (_, e))| (r, e)).collect();
+                assert_eq!(ctx.value(&ctx.multi_pow(&args)), expected % &n);
+            }
+        }
+    }
+}
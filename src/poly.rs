@@ -0,0 +1,408 @@
+use crate::math::inv_egcd;
+use crate::prime::wheel_primes;
+use num_bigint::{BigInt, BigUint, Sign};
+
+// a polynomial over the integers, the coefficients go from x^0 up
+#[derive(Clone, Debug, PartialEq)]
+pub struct Poly(Vec<BigInt>);
+
+// floor division, unlike / on BigInt which truncates
+pub fn div_floor(a: &BigInt, b: &BigInt) -> BigInt {
+    let (q, r) = (a / b, a % b);
+    match r.sign() != Sign::NoSign && (r.sign() == Sign::Minus) != (b.sign() == Sign::Minus) {
+        true => q - 1,
+        false => q,
+    }
+}
+
+// reduced into [0, m)
+pub fn modulo(a: &BigInt, m: &BigUint) -> BigUint {
+    let m = BigInt::from(m.clone());
+    (a - div_floor(a, &m) * &m)
+        .to_biguint()
+        .expect("a floored remainder is not negative")
+}
+
+impl Poly {
+    pub fn new(coeffs: Vec<BigInt>) -> Self {
+        let mut p = Self(coeffs);
+        while p.0.last().is_some_and(|c| c.sign() == Sign::NoSign) {
+            p.0.pop();
+        }
+        p
+    }
+
+    pub fn zero() -> Self {
+        Self(vec![])
+    }
+
+    pub fn constant(c: BigInt) -> Self {
+        Self::new(vec![c])
+    }
+
+    // x - a
+    pub fn linear(a: BigInt) -> Self {
+        Self::new(vec![-a, 1.into()])
+    }
+
+    pub fn coeffs(&self) -> &[BigInt] {
+        &self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // the zero polynomial has no degree
+    pub fn degree(&self) -> Option<usize> {
+        self.0.len().checked_sub(1)
+    }
+
+    pub fn eval(&self, x: &BigInt) -> BigInt {
+        self.0
+            .iter()
+            .rev()
+            .fold(BigInt::from(0), |acc, c| acc * x + c)
+    }
+
+    pub fn add(&self, other: &Poly) -> Poly {
+        let len = self.0.len().max(other.0.len());
+        let zero = BigInt::from(0);
+        let coeff = |p: &Poly, i| p.0.get(i).unwrap_or(&zero).clone();
+        Self::new((0..len).map(|i| coeff(self, i) + coeff(other, i)).collect())
+    }
+
+    pub fn sub(&self, other: &Poly) -> Poly {
+        self.add(&other.scale(&BigInt::from(-1)))
+    }
+
+    pub fn mul(&self, other: &Poly) -> Poly {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero();
+        }
+        let mut coeffs = vec![BigInt::from(0); self.0.len() + other.0.len() - 1];
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in other.0.iter().enumerate() {
+                coeffs[i + j] += a * b;
+            }
+        }
+        Self::new(coeffs)
+    }
+
+    pub fn scale(&self, c: &BigInt) -> Poly {
+        Self::new(self.0.iter().map(|a| a * c).collect())
+    }
+
+    pub fn pow(&self, k: u32) -> Poly {
+        (0..k).fold(Self::constant(1.into()), |acc, _| acc.mul(self))
+    }
+
+    // p(a x)
+    pub fn scale_var(&self, a: &BigInt) -> Poly {
+        let mut ak = BigInt::from(1);
+        let mut coeffs = vec![];
+        for c in &self.0 {
+            coeffs.push(c * &ak);
+            ak *= a;
+        }
+        Self::new(coeffs)
+    }
+
+    // p(q(x))
+    pub fn compose(&self, q: &Poly) -> Poly {
+        self.0.iter().rev().fold(Self::zero(), |acc, c| {
+            acc.mul(q).add(&Self::constant(c.clone()))
+        })
+    }
+
+    pub fn derivative(&self) -> Poly {
+        let coeffs = self.0.iter().enumerate().skip(1);
+        Self::new(coeffs.map(|(i, c)| c * i).collect())
+    }
+
+    // the quotient when other divides self over the integers
+    pub fn div_exact(&self, other: &Poly) -> Option<Poly> {
+        let d = other.degree()?;
+        let lead = &other.0[d];
+        let mut rem = self.clone();
+        let mut quo = vec![BigInt::from(0); self.0.len().saturating_sub(d)];
+        while let Some(r) = rem.degree().filter(|r| *r >= d) {
+            let c = &rem.0[r] / lead;
+            if &c * lead != rem.0[r] {
+                return None;
+            }
+            let mut shifted = vec![BigInt::from(0); r - d];
+            shifted.extend(other.0.iter().map(|o| o * &c));
+            rem = rem.sub(&Self::new(shifted));
+            quo[r - d] = c;
+        }
+        rem.is_zero().then(|| Self::new(quo))
+    }
+
+    // the coefficients reduced into [0, n)
+    pub fn reduce(&self, n: &BigUint) -> Poly {
+        Self::new(self.0.iter().map(|c| modulo(c, n).into()).collect())
+    }
+
+    // the remainder by other mod n, none when the leading coefficient of other
+    // is not invertible
+    pub fn rem_mod(&self, other: &Poly, n: &BigUint) -> Option<Poly> {
+        let d = other.degree()?;
+        let inv = BigInt::from(inv_egcd(&modulo(&other.0[d], n), n)?);
+        let mut rem = self.reduce(n);
+        while let Some(r) = rem.degree().filter(|r| *r >= d) {
+            let mut shifted = vec![BigInt::from(0); r - d];
+            shifted.push(&rem.0[r] * &inv);
+            rem = rem.sub(&other.mul(&Self::new(shifted))).reduce(n);
+        }
+        Some(rem)
+    }
+
+    // the monic gcd mod n, by euclid's algorithm as if n were a prime
+    pub fn gcd_mod(&self, other: &Poly, n: &BigUint) -> Option<Poly> {
+        let (mut a, mut b) = (self.reduce(n), other.reduce(n));
+        while !b.is_zero() {
+            let r = a.rem_mod(&b, n)?;
+            a = b;
+            b = r;
+        }
+        let d = a.degree()?;
+        let inv = BigInt::from(inv_egcd(&modulo(&a.0[d], n), n)?);
+        Some(a.scale(&inv).reduce(n))
+    }
+
+    // cauchy's bound on the absolute value of the roots
+    fn root_bound(&self) -> BigUint {
+        let lead = self.0[self.0.len() - 1].magnitude();
+        let max = self
+            .0
+            .iter()
+            .map(|c| c.magnitude())
+            .max()
+            .expect("not zero");
+        max / lead + 2u8
+    }
+
+    // the integer roots, the simple ones found by lifting the roots mod a few
+    // small primes and the repeated ones as the roots of the derivative
+    pub fn integer_roots(&self) -> Vec<BigInt> {
+        let mut roots = vec![];
+        let mut p = self.clone();
+        if p.degree().unwrap_or(0) == 0 {
+            return roots;
+        }
+        if p.0[0].sign() == Sign::NoSign {
+            roots.push(BigInt::from(0));
+            while p.0[0].sign() == Sign::NoSign {
+                p.0.remove(0);
+            }
+        }
+        let bound = BigUint::from(2u8) * p.root_bound();
+        let dp = p.derivative();
+        // a root is lost for a prime that divides p'(root), so a few are tried
+        let primes = wheel_primes(1 << 8);
+        for l in primes.into_iter().rev().take(4) {
+            let l = BigUint::from(l);
+            let reduced = Self::new(p.0.iter().map(|c| modulo(c, &l).into()).collect());
+            let mut r = BigUint::from(0u8);
+            while r < l {
+                let ri = BigInt::from(r.clone());
+                if modulo(&reduced.eval(&ri), &l) == 0u8.into() {
+                    if let Some(root) = p.lift_root(&dp, &ri, &l, &bound) {
+                        if !roots.contains(&root) {
+                            roots.push(root);
+                        }
+                    }
+                }
+                r += 1u8;
+            }
+        }
+        for root in dp.integer_roots() {
+            if p.eval(&root).sign() == Sign::NoSign && !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+        roots.sort();
+        roots
+    }
+
+    // newton's method on a root mod l, up to a modulus above bound
+    fn lift_root(&self, dp: &Poly, r: &BigInt, l: &BigUint, bound: &BigUint) -> Option<BigInt> {
+        let (mut r, mut m) = (modulo(r, l), l.clone());
+        inv_egcd(&modulo(&dp.eval(&r.clone().into()), l), l)?;
+        while &m <= bound {
+            m = &m * &m;
+            let ri = BigInt::from(r.clone());
+            let inv = inv_egcd(&modulo(&dp.eval(&ri), &m), &m)?;
+            let step = modulo(&(self.eval(&ri) * BigInt::from(inv)), &m);
+            r = (r + &m - step) % &m;
+        }
+        // the symmetric representative
+        let mut root = BigInt::from(r.clone());
+        if r > &m >> 1 {
+            root -= BigInt::from(m);
+        }
+        (self.eval(&root).sign() == Sign::NoSign).then_some(root)
+    }
+}
+
+// a polynomial in x and y as polynomials in y, the coefficients of x^0 up
+pub type BiPoly = Vec<Poly>;
+
+fn bi_trim(mut f: BiPoly) -> BiPoly {
+    while f.last().is_some_and(|c| c.is_zero()) {
+        f.pop();
+    }
+    f
+}
+
+pub fn bi_add(f: &BiPoly, g: &BiPoly) -> BiPoly {
+    let mut res = vec![Poly::zero(); f.len().max(g.len())];
+    for (a, fa) in f.iter().enumerate() {
+        res[a] = fa.clone();
+    }
+    for (a, ga) in g.iter().enumerate() {
+        res[a] = res[a].add(ga);
+    }
+    bi_trim(res)
+}
+
+pub fn bi_mul(f: &BiPoly, g: &BiPoly) -> BiPoly {
+    if f.is_empty() || g.is_empty() {
+        return vec![];
+    }
+    let mut res = vec![Poly::zero(); f.len() + g.len() - 1];
+    for (a, fa) in f.iter().enumerate() {
+        for (b, gb) in g.iter().enumerate() {
+            res[a + b] = res[a + b].add(&fa.mul(gb));
+        }
+    }
+    bi_trim(res)
+}
+
+// bareiss' fraction-free elimination, every division is exact
+fn det(mut m: Vec<Vec<Poly>>) -> Poly {
+    let n = m.len();
+    let mut prev = Poly::constant(1.into());
+    let mut sign = BigInt::from(1);
+    for k in 0..n {
+        match (k..n).find(|i| !m[*i][k].is_zero()) {
+            Some(i) if i != k => {
+                m.swap(i, k);
+                sign = -sign;
+            }
+            Some(_) => {}
+            None => return Poly::zero(),
+        }
+        for i in k + 1..n {
+            for j in k + 1..n {
+                let t = m[i][j].mul(&m[k][k]).sub(&m[i][k].mul(&m[k][j]));
+                m[i][j] = t.div_exact(&prev).expect("bareiss divides exactly");
+            }
+        }
+        prev = m[k][k].clone();
+    }
+    prev.scale(&sign)
+}
+
+// the resultant in x, a polynomial in y that vanishes at the y of common roots
+pub fn resultant_x(p: &BiPoly, q: &BiPoly) -> Poly {
+    if p.is_empty() || q.is_empty() {
+        return Poly::zero();
+    }
+    let (dp, dq) = (p.len() - 1, q.len() - 1);
+    let n = dp + dq;
+    if n == 0 {
+        return Poly::constant(1.into());
+    }
+    // sylvester's matrix, shifted copies of the coefficients from the top down
+    let mut rows = vec![];
+    for (poly, shifts) in [(p, dq), (q, dp)] {
+        for s in 0..shifts {
+            let mut row = vec![Poly::zero(); n];
+            for (i, c) in poly.iter().rev().enumerate() {
+                row[s + i] = c.clone();
+            }
+            rows.push(row);
+        }
+    }
+    det(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coeffs: &[i64]) -> Poly {
+        Poly::new(coeffs.iter().map(|c| BigInt::from(*c)).collect())
+    }
+
+    #[test]
+    fn poly_works() {
+        let p = poly(&[1, 2, 3]);
+        let q = poly(&[-1, 1]);
+        assert_eq!(p.mul(&q), poly(&[-1, -1, -1, 3]));
+        assert_eq!(p.mul(&q).div_exact(&q), Some(p.clone()));
+        assert_eq!(p.div_exact(&q), None);
+        assert_eq!(p.add(&poly(&[0, 0, -3])), poly(&[1, 2]));
+        assert_eq!(p.eval(&BigInt::from(-2)), BigInt::from(9));
+        assert_eq!(p.derivative(), poly(&[2, 6]));
+        assert_eq!(p.compose(&q), poly(&[2, -4, 3]));
+        assert_eq!(p.scale_var(&BigInt::from(2)), poly(&[1, 4, 12]));
+        assert_eq!(q.pow(3), poly(&[-1, 3, -3, 1]));
+        assert_eq!(
+            div_floor(&BigInt::from(-7), &BigInt::from(2)),
+            BigInt::from(-4)
+        );
+        assert_eq!(modulo(&BigInt::from(-7), &5u8.into()), 3u8.into());
+    }
+
+    #[test]
+    fn gcd_mod_works() {
+        let n = BigUint::from(101u8);
+        // (x - 3) (x + 5) and 2 (x - 3) (x^2 + 1)
+        let p = Poly::linear(3.into()).mul(&poly(&[5, 1]));
+        let q = Poly::linear(3.into()).mul(&poly(&[2, 0, 2]));
+        assert_eq!(p.gcd_mod(&q, &n), Some(poly(&[98, 1])));
+        assert_eq!(q.rem_mod(&p, &n), Some(poly(&[46, 52])));
+        assert_eq!(p.rem_mod(&poly(&[]), &n), None);
+    }
+
+    #[test]
+    fn integer_roots_works() {
+        // (x + 12345678901234567) (x - 3) (2x + 1) (x^2 + 1)
+        let big = BigInt::from(12345678901234567i64);
+        let p = Poly::linear(-big.clone())
+            .mul(&Poly::linear(3.into()))
+            .mul(&poly(&[1, 2]))
+            .mul(&poly(&[1, 0, 1]));
+        assert_eq!(p.integer_roots(), vec![-big, 3.into()]);
+        assert_eq!(
+            poly(&[0, 0, 5, 1]).integer_roots(),
+            vec![(-5).into(), 0.into()]
+        );
+        assert!(poly(&[7]).integer_roots().is_empty());
+        // (x - 4)^3 (x + 1)
+        let p = Poly::linear(4.into()).pow(3).mul(&poly(&[1, 1]));
+        assert_eq!(p.integer_roots(), vec![(-1).into(), 4.into()]);
+    }
+
+    #[test]
+    fn resultant_x_works() {
+        // x + y - 5 and x - y - 1 meet at y = 2
+        let p = vec![poly(&[-5, 1]), poly(&[1])];
+        let q = vec![poly(&[-1, -1]), poly(&[1])];
+        let r = resultant_x(&p, &q);
+        assert_eq!(r.integer_roots(), vec![2.into()]);
+        // x^2 - y and x - 3 meet at y = 9
+        let p = vec![poly(&[0, -1]), poly(&[]), poly(&[1])];
+        let q = vec![poly(&[-3]), poly(&[1])];
+        assert_eq!(resultant_x(&p, &q), poly(&[9, -1]));
+        // (x + y) (x - y) = x^2 - y^2
+        let p = vec![poly(&[0, 1]), poly(&[1])];
+        let q = vec![poly(&[0, -1]), poly(&[1])];
+        let pq = vec![poly(&[0, 0, -1]), poly(&[]), poly(&[1])];
+        assert_eq!(bi_mul(&p, &q), pq);
+        assert_eq!(bi_add(&p, &q), vec![poly(&[]), poly(&[2])]);
+    }
+}
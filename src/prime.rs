@@ -1,3 +1,4 @@
+use crate::math::{crt, gcd, inv_egcd, nth_root};
 use num_bigint::{BigUint, RandBigInt};
 use rand::Rng;
 
@@ -43,6 +44,10 @@ fn filter_wheel(coprimes: Vec<usize>, limit: usize) -> Vec<usize> {
 }
 
 pub fn wheel_primes(limit: usize) -> Vec<usize> {
+    // the wheel holds every coprime below limit, the sieve only one segment
+    if limit > 1 << 16 {
+        return primes(limit as u64).map(|p| p as usize).collect();
+    }
     let mut primes: Vec<usize> = vec![2, 3];
     let mut coprimes = vec![1, 5];
     while !turn_wheel(&mut primes, &mut coprimes, limit) {}
@@ -50,20 +55,149 @@ pub fn wheel_primes(limit: usize) -> Vec<usize> {
     primes
 }
 
+// the odd numbers a segment of the sieve covers
+const SEGMENT: u64 = 1 << 18;
+
+fn isqrt(n: u64) -> u64 {
+    let mut r = (n as f64).sqrt() as u64;
+    while r as u128 * r as u128 > n as u128 {
+        r -= 1;
+    }
+    while (r + 1) as u128 * (r + 1) as u128 <= n as u128 {
+        r += 1;
+    }
+    r
+}
+
+// the primes in [lo, hi), crossing out the odd multiples of the base primes,
+// which have to hold every prime up to sqrt(hi)
+fn sieve_segment(lo: u64, hi: u64, base: &[u64]) -> Vec<u64> {
+    let mut primes = match lo <= 2 && 2 < hi {
+        true => vec![2],
+        false => vec![],
+    };
+    // index i stands for first + 2 i
+    let first = lo.max(3) | 1;
+    if first >= hi {
+        return primes;
+    }
+    let len = (hi - first).div_ceil(2) as usize;
+    let mut composite = vec![false; len];
+    for &p in base.iter().skip_while(|p| **p == 2) {
+        if p as u128 * p as u128 >= hi as u128 {
+            break;
+        }
+        let mut m = (p * p).max(first.div_ceil(p) * p);
+        if m % 2 == 0 {
+            m += p;
+        }
+        for i in (((m - first) / 2) as usize..len).step_by(p as usize) {
+            composite[i] = true;
+        }
+    }
+    let odd = (0..len).filter(|i| !composite[*i]);
+    primes.extend(odd.map(|i| first + 2 * i as u64));
+    primes
+}
+
+// the primes in [lo, hi) in order, sieved one segment at a time so memory stays
+// at a segment and the primes up to sqrt(hi)
+pub struct Primes {
+    base: Vec<u64>,
+    bound: u64,
+    lo: u64,
+    hi: u64,
+    segment: std::vec::IntoIter<u64>,
+}
+
+pub fn primes_between(lo: u64, hi: u64) -> Primes {
+    Primes {
+        base: vec![],
+        bound: 3,
+        lo,
+        hi,
+        segment: vec![].into_iter(),
+    }
+}
+
+pub fn primes(limit: u64) -> Primes {
+    primes_between(0, limit)
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(p) = self.segment.next() {
+                return Some(p);
+            }
+            if self.lo >= self.hi {
+                return None;
+            }
+            let hi = self.hi.min(self.lo.saturating_add(2 * SEGMENT));
+            // the base primes lie below bound, twice the root for room to grow
+            if (self.bound as u128) * (self.bound as u128) < hi as u128 {
+                self.bound = 2 * isqrt(hi) + 1;
+                self.base = primes(self.bound).collect();
+            }
+            self.segment = sieve_segment(self.lo, hi, &self.base).into_iter();
+            self.lo = hi;
+        }
+    }
+}
+
+// the primes below limit that divide n, with the segments spread over the cores
+fn prime_divisors(n: &BigUint, limit: u64) -> Vec<u64> {
+    let digits = n.to_u64_digits();
+    let divides = |p: u64| {
+        let rem = |r: u128, d: &u64| ((r << 64) | *d as u128) % p as u128;
+        digits.iter().rev().fold(0, rem) == 0
+    };
+    let base: Vec<_> = primes(isqrt(limit) + 1).collect();
+    let segments = limit.div_ceil(2 * SEGMENT);
+    let cores = std::thread::available_parallelism().map_or(1, |c| c.get()) as u64;
+    let threads = cores.min(segments);
+    let mut found: Vec<u64> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let (base, divides) = (&base, &divides);
+                s.spawn(move || {
+                    let mut found = vec![];
+                    for i in (t..segments).step_by(threads as usize) {
+                        let lo = i * 2 * SEGMENT;
+                        let hi = limit.min(lo + 2 * SEGMENT);
+                        found.extend(
+                            sieve_segment(lo, hi, base)
+                                .into_iter()
+                                .filter(|p| divides(*p)),
+                        );
+                    }
+                    found
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("a sieve thread panicked"))
+            .collect()
+    });
+    found.sort();
+    found
+}
+
+// the primes below limit that divide n, with their exponents
 pub fn factors_up_to(n: &BigUint, limit: usize) -> Vec<(usize, u32)> {
     let mut factors = vec![];
-    let primes = wheel_primes(limit);
     let zero = BigUint::from(0u8);
     let mut n = n.clone();
-    for p in primes {
+    for p in prime_divisors(&n, limit as u64) {
         let mut exp = 0;
         while &n % p == zero {
             n /= p;
             exp += 1;
         }
-        if exp > 0 {
-            factors.push((p, exp));
-        }
+        factors.push((p as usize, exp));
     }
     factors
 }
@@ -105,28 +239,71 @@ pub fn divided_by_small_prime(n: &BigUint) -> bool {
     true
 }
 
+// n is a strong probable prime to base a: with n - 1 = d 2^r and d odd, either
+// a^d = 1 or a^(d 2^i) = -1 mod n for some i < r
+pub fn strong_probable_prime(n: &BigUint, a: &BigUint) -> bool {
+    let one = BigUint::from(1u8);
+    let n_minus_1 = n - &one;
+    let (r, d) = fact_pow2(n_minus_1.clone());
+    let mut x = a.modpow(&d, n);
+    if x == one || x == n_minus_1 {
+        return true;
+    }
+    for _ in 1..r {
+        x = &x * &x % n;
+        if x == one {
+            return false;
+        }
+        if x == n_minus_1 {
+            return true;
+        }
+    }
+    false
+}
+
 pub fn mrp_check<R: Rng>(n: &BigUint, k: usize, rng: &mut R) -> bool {
     if !divided_by_small_prime(n) {
         return false;
     }
-    let one = BigUint::from(1u8);
     let two = BigUint::from(2u8);
-    let n_minus_1 = n.clone() - &one;
     let n_minus_2 = n.clone() - &two;
-    let (r, d) = fact_pow2(n_minus_1.clone());
+    (0..k).all(|_| strong_probable_prime(n, &rng.gen_biguint_range(&two, &n_minus_2)))
+}
 
-    'witness: for _ in 0..k {
-        let a = rng.gen_biguint_range(&two, &n_minus_2);
-        let mut x = a.modpow(&d, n);
-        if x == one || x == n_minus_1 {
-            continue;
+// miller-rabin with the first twelve primes as bases, which no composite below
+// 2^64 passes
+pub fn is_prime_u64(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
         }
-        for _ in 0..r - 1 {
-            x = x.modpow(&2u8.into(), n);
-            if x == one {
-                return false;
+    }
+    if n < 2 {
+        return false;
+    }
+    let mul = |a: u64, b: u64| (a as u128 * b as u128 % n as u128) as u64;
+    let pow = |mut a: u64, mut e: u64| {
+        let mut x = 1;
+        while e > 0 {
+            if e & 1 == 1 {
+                x = mul(x, a);
             }
-            if x == n_minus_1 {
+            a = mul(a, a);
+            e >>= 1;
+        }
+        x
+    };
+    let r = (n - 1).trailing_zeros();
+    let d = (n - 1) >> r;
+    'witness: for a in BASES {
+        let mut x = pow(a, d);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..r {
+            x = mul(x, x);
+            if x == n - 1 {
                 continue 'witness;
             }
         }
@@ -135,6 +312,178 @@ pub fn mrp_check<R: Rng>(n: &BigUint, k: usize, rng: &mut R) -> bool {
     true
 }
 
+// the jacobi symbol (a / n) for an odd n
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i8 {
+    let low = |x: &BigUint| x.iter_u32_digits().next().unwrap_or(0);
+    let (mut a, mut n) = (a % n, n.clone());
+    let mut t = 1;
+    while a != BigUint::from(0u8) {
+        while !a.bit(0) {
+            a >>= 1;
+            if matches!(low(&n) % 8, 3 | 5) {
+                t = -t;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        if low(&a) % 4 == 3 && low(&n) % 4 == 3 {
+            t = -t;
+        }
+        a %= &n;
+    }
+    match n == BigUint::from(1u8) {
+        true => t,
+        false => 0,
+    }
+}
+
+// the strong lucas probable prime test for an odd n with selfridge's parameters:
+// d the first of 5, -7, 9, -11, ... with (d / n) = -1, p = 1 and q = (1 - d) / 4.
+// with n + 1 = k 2^s and k odd, either u_k = 0 or v_(k 2^i) = 0 mod n for some i < s
+pub fn strong_lucas_probable_prime(n: &BigUint) -> bool {
+    // no such d exists for a square
+    let root = nth_root(n.clone(), 2);
+    if &root * &root == *n {
+        return false;
+    }
+    let to_mod = |x: i64| match x < 0 {
+        true => n - (BigUint::from(x.unsigned_abs()) % n),
+        false => BigUint::from(x as u64) % n,
+    };
+    let mut d: i64 = 5;
+    loop {
+        match jacobi(&to_mod(d), n) {
+            -1 => break,
+            // a factor of n, unless it is n itself
+            0 => return *n == BigUint::from(d.unsigned_abs()),
+            _ => d = if d > 0 { -d - 2 } else { -d + 2 },
+        }
+    }
+    let (dm, qm) = (to_mod(d), to_mod((1 - d) / 4));
+    let (s, k) = fact_pow2(n + 1u8);
+    let half = |x: BigUint| match x.bit(0) {
+        true => (x + n) >> 1,
+        false => x >> 1,
+    };
+    // v_2j = v_j^2 - 2 q^j
+    let double = |v: &BigUint, qj: &BigUint| (v * v + (n << 1) - (qj << 1)) % n;
+    // u_j, v_j and q^j from j = 1, walking down the bits of k
+    let (mut u, mut v, mut qj) = (BigUint::from(1u8), BigUint::from(1u8), qm.clone());
+    for i in (0..k.bits() - 1).rev() {
+        u = &u * &v % n;
+        v = double(&v, &qj);
+        qj = &qj * &qj % n;
+        if k.bit(i) {
+            // u_(j+1) = (u_j + v_j) / 2 and v_(j+1) = (d u_j + v_j) / 2
+            let u1 = half((&u + &v) % n);
+            v = half((&dm * &u + &v) % n);
+            u = u1;
+            qj = &qj * &qm % n;
+        }
+    }
+    let zero = BigUint::from(0u8);
+    if u == zero || v == zero {
+        return true;
+    }
+    for _ in 1..s {
+        v = double(&v, &qj);
+        qj = &qj * &qj % n;
+        if v == zero {
+            return true;
+        }
+    }
+    false
+}
+
+// baillie-psw: trial division, a strong probable prime test to base 2 and a
+// strong lucas test. no composite is known to pass both
+pub fn baillie_psw(n: &BigUint) -> bool {
+    if *n < BigUint::from(2u8) {
+        return false;
+    }
+    for p in std::iter::once(2).chain(SMALL_PRIMES) {
+        if n % p == BigUint::from(0u8) {
+            return *n == BigUint::from(p);
+        }
+    }
+    strong_probable_prime(n, &2u8.into()) && strong_lucas_probable_prime(n)
+}
+
+// arnault's construction of a composite n = p1 p2 p3, with p_i = k_i (p1 - 1) + 1,
+// that is a strong probable prime to every prime base below max_base. each p_i is
+// 3 mod 4 with (a / p_i) = -1 for those bases and p_i - 1 divides n - 1, so
+// a^((n - 1) / 2) = -1 mod n, which is why a fixed set of bases is not enough
+// against numbers someone built on purpose
+pub fn arnault_pseudoprime(max_base: u32, rng: &mut impl rand::Rng) -> (BigUint, [BigUint; 3]) {
+    let bases: Vec<_> = wheel_primes(max_base as usize)
+        .into_iter()
+        .filter(|a| *a > 2 && *a < max_base as usize)
+        .collect();
+    let legendre = |x: usize, a: usize| jacobi(&BigUint::from(x), &BigUint::from(a));
+    // the k_i are primes 1 mod 4 above the bases, so that (k_i / a) = (a / k_i)
+    let ks: Vec<_> = wheel_primes(8 * max_base as usize + 64)
+        .into_iter()
+        .filter(|k| *k > max_base as usize && k % 4 == 1)
+        .collect();
+    for (i, &k2) in ks.iter().enumerate() {
+        for &k3 in &ks[i + 1..] {
+            // (a / p_i) = -1 for each p_i is (p_i / a) = -1 when a = 1 mod 4 and
+            // (p_i / a) = 1 when a = 3 mod 4, with p1 = 3 mod 8 to keep p_i = 3 mod 4
+            let residues: Vec<Vec<usize>> = bases
+                .iter()
+                .map(|&a| {
+                    let target = if a % 4 == 1 { -1 } else { 1 };
+                    (0..a)
+                        .filter(|r| {
+                            [1, k2, k3]
+                                .iter()
+                                .all(|k| legendre((k * (r + a - 1) + 1) % a, a) == target)
+                        })
+                        .collect()
+                })
+                .collect();
+            if residues.iter().any(|r| r.is_empty()) {
+                continue;
+            }
+            let inv = |x: usize, m: usize| {
+                inv_egcd(&x.into(), &m.into()).expect("k2 and k3 are distinct primes")
+            };
+            let mut congruences = vec![
+                (3u8.into(), 8u8.into()),
+                (BigUint::from(k2) - inv(k3, k2), k2.into()),
+                (BigUint::from(k3) - inv(k2, k3), k3.into()),
+            ];
+            for (&a, r) in bases.iter().zip(&residues) {
+                congruences.push((r[rng.gen_range(0..r.len())].into(), a.into()));
+            }
+            let (mut p1, m) = crt(&congruences);
+            loop {
+                let p2 = (&p1 - 1u8) * k2 + 1u8;
+                let p3 = (&p1 - 1u8) * k3 + 1u8;
+                if baillie_psw(&p1) && baillie_psw(&p2) && baillie_psw(&p3) {
+                    return (&p1 * &p2 * &p3, [p1, p2, p3]);
+                }
+                p1 += &m;
+            }
+        }
+    }
+    unreachable!("some pair k2, k3 fits the bases")
+}
+
+// a prime p = 2q + 1 with q prime too, so the only small subgroup mod p is {1, -1}
+pub fn safe_prime(size: u64, k: usize, rng: &mut impl rand::Rng) -> BigUint {
+    loop {
+        let mut q = rng.gen_biguint(size - 1);
+        q.set_bit(size - 2, true);
+        q.set_bit(0, true);
+        let p: BigUint = (&q << 1) + 1u8;
+        // both are sieved before the first costly test
+        let sieved = divided_by_small_prime(&q) && divided_by_small_prime(&p);
+        if sieved && mrp_check(&q, k, rng) && mrp_check(&p, k, rng) {
+            return p;
+        }
+    }
+}
+
 pub fn pq(p_bits: u64, q_bits: u64, k: usize, rng: &mut impl rand::Rng) -> (BigUint, BigUint) {
     assert!(q_bits < p_bits - 3, "q must be << p");
     let q = mr_prime(q_bits, k, rng);
@@ -148,6 +497,188 @@ pub fn pq(p_bits: u64, q_bits: u64, k: usize, rng: &mut impl rand::Rng) -> (BigU
     (p, q)
 }
 
+// a proof that n is prime, checked by verify_certificate without any randomness
+#[derive(Clone, Debug, PartialEq)]
+pub enum Certificate {
+    // below 2^64, where is_prime_u64 is exact
+    Small(u64),
+    // pocklington: with f the part of n - 1 made of the proven primes q, f^2 > n,
+    // a^(n - 1) = 1 and gcd(a^((n - 1) / q) - 1, n) = 1 for each q
+    Pocklington {
+        n: BigUint,
+        witness: BigUint,
+        factors: Vec<Certificate>,
+    },
+}
+
+impl Certificate {
+    // the number it proves prime
+    pub fn prime(&self) -> BigUint {
+        match self {
+            Certificate::Small(n) => BigUint::from(*n),
+            Certificate::Pocklington { n, .. } => n.clone(),
+        }
+    }
+}
+
+pub fn verify_certificate(cert: &Certificate) -> bool {
+    let Certificate::Pocklington {
+        n,
+        witness,
+        factors,
+    } = cert
+    else {
+        return matches!(cert, Certificate::Small(n) if is_prime_u64(*n));
+    };
+    let one = BigUint::from(1u8);
+    let zero = BigUint::from(0u8);
+    if n <= &one || !n.bit(0) {
+        return false;
+    }
+    let n_minus_1 = n - &one;
+    let mut f = one.clone();
+    let mut seen = vec![];
+    for c in factors {
+        let q = c.prime();
+        // a repeated prime would count twice towards f
+        if seen.contains(&q) || &n_minus_1 % &q != zero || !verify_certificate(c) {
+            return false;
+        }
+        let mut rest = n_minus_1.clone();
+        while &rest % &q == zero {
+            rest /= &q;
+            f *= &q;
+        }
+        let x = witness.modpow(&(&n_minus_1 / &q), n);
+        if gcd(&((x + &n_minus_1) % n), n) != one {
+            return false;
+        }
+        seen.push(q);
+    }
+    &f * &f > *n && witness.modpow(&n_minus_1, n) == one
+}
+
+// a base for pocklington's test of n with the primes qs of n - 1, none if n is
+// shown composite or no small base does it
+fn pocklington_witness(n: &BigUint, qs: &[BigUint]) -> Option<BigUint> {
+    let one = BigUint::from(1u8);
+    let n_minus_1 = n - &one;
+    for a in 2u8..100 {
+        let a = BigUint::from(a);
+        if a.modpow(&n_minus_1, n) != one {
+            return None;
+        }
+        let coprime = |q: &BigUint| {
+            let x = a.modpow(&(&n_minus_1 / q), n);
+            gcd(&((x + &n_minus_1) % n), n) == one
+        };
+        if qs.iter().all(coprime) {
+            return Some(a);
+        }
+    }
+    None
+}
+
+// a prime n = 2 r f + 1 of size bits that accept agrees with, where f is the
+// product of the proven primes and must be above 2^((size + 1) / 2)
+fn pocklington_prime(
+    size: u64,
+    factors: Vec<Certificate>,
+    accept: impl Fn(&BigUint) -> bool,
+    rng: &mut impl rand::Rng,
+) -> (BigUint, Certificate) {
+    let qs: Vec<_> = factors.iter().map(Certificate::prime).collect();
+    let f2 = qs.iter().product::<BigUint>() << 1;
+    let low = (BigUint::from(1u8) << (size - 1)) / &f2 + 1u8;
+    let high = (BigUint::from(1u8) << size) / &f2;
+    loop {
+        let n = rng.gen_biguint_range(&low, &high) * &f2 + 1u8;
+        if !divided_by_small_prime(&n) || !strong_probable_prime(&n, &2u8.into()) || !accept(&n) {
+            continue;
+        }
+        if let Some(witness) = pocklington_witness(&n, &qs) {
+            let cert = Certificate::Pocklington {
+                n: n.clone(),
+                witness,
+                factors,
+            };
+            return (n, cert);
+        }
+    }
+}
+
+// maurer's way to a random prime of size bits that comes with its certificate:
+// n - 1 is built around a proven prime of a bit more than half the size
+pub fn provable_prime(size: u64, rng: &mut impl rand::Rng) -> (BigUint, Certificate) {
+    if size <= 64 {
+        loop {
+            let n = rng.gen::<u64>() >> (64 - size) | 1 << (size - 1) | 1;
+            if is_prime_u64(n) {
+                return (n.into(), Certificate::Small(n));
+            }
+        }
+    }
+    let (_, cq) = provable_prime(size.div_ceil(2) + 1, rng);
+    pocklington_prime(size, vec![cq], |_| true, rng)
+}
+
+// a safe prime p = 2q + 1 with the certificates of both, p is proven by q alone
+pub fn provable_safe_prime(size: u64, rng: &mut impl rand::Rng) -> (BigUint, Certificate) {
+    let (_, cs) = provable_prime(size / 2 + 2, rng);
+    let (q, cq) = pocklington_prime(size - 1, vec![cs], |q| baillie_psw(&((q << 1) + 1u8)), rng);
+    let p: BigUint = (&q << 1) + 1u8;
+    let witness = pocklington_witness(&p, &[q]).expect("p passed baillie-psw");
+    let cert = Certificate::Pocklington {
+        n: p.clone(),
+        witness,
+        factors: vec![cq],
+    };
+    (p, cert)
+}
+
+// p of p_bits and q of q_bits with q dividing p - 1 as pq does, both proven.
+// p - 1 holds q and a proven prime above sqrt(p)
+pub fn provable_pq(
+    p_bits: u64,
+    q_bits: u64,
+    rng: &mut impl rand::Rng,
+) -> (BigUint, BigUint, Certificate, Certificate) {
+    let s_bits = p_bits.div_ceil(2) + 1;
+    assert!(q_bits + s_bits + 2 < p_bits, "q must be << p");
+    let (q, cq) = provable_prime(q_bits, rng);
+    let (_, cs) = provable_prime(s_bits, rng);
+    let (p, cp) = pocklington_prime(p_bits, vec![cq.clone(), cs], |_| true, rng);
+    (p, q, cp, cq)
+}
+
+// the certificate of a prime whose n - 1 factors into primes below 2^16 and at
+// most one larger prime that can itself be certified
+pub fn certify(n: &BigUint) -> Option<Certificate> {
+    if let Ok(n) = u64::try_from(n) {
+        return is_prime_u64(n).then_some(Certificate::Small(n));
+    }
+    let n_minus_1 = n - 1u8;
+    let mut rest = n_minus_1.clone();
+    let mut factors = vec![];
+    for (q, e) in factors_up_to(&n_minus_1, 1 << 16) {
+        rest /= BigUint::from(q).pow(e);
+        factors.push(Certificate::Small(q as u64));
+    }
+    if rest > 1u8.into() {
+        if !baillie_psw(&rest) {
+            return None;
+        }
+        factors.push(certify(&rest)?);
+    }
+    let qs: Vec<_> = factors.iter().map(Certificate::prime).collect();
+    let witness = pocklington_witness(n, &qs)?;
+    Some(Certificate::Pocklington {
+        n: n.clone(),
+        witness,
+        factors,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +726,28 @@ mod tests {
         assert_eq!(facts, vec![(2, 3), (17, 1), (103, 5), (241, 2)]);
     }
 
+    #[test]
+    fn primes_works() {
+        let w: Vec<_> = wheel_primes(10000).into_iter().map(|p| p as u64).collect();
+        assert_eq!(primes(10000).collect::<Vec<_>>(), w);
+        assert_eq!(primes(3).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(primes(10_000_000).count(), 664579);
+        let lo = 1_000_000_000_000;
+        let expected: Vec<_> = (lo..lo + 10000).filter(|n| is_prime_u64(*n)).collect();
+        assert_eq!(primes_between(lo, lo + 10000).collect::<Vec<_>>(), expected);
+        assert_eq!(wheel_primes(1 << 20).len(), 82025);
+    }
+
+    #[test]
+    fn factors_up_to_many_segments_works() {
+        // 2^25 - 39 and 2^24 - 3 are primes far past the first segment
+        let n = BigUint::from(3u8).pow(4) * ((1u64 << 25) - 39) * ((1u64 << 24) - 3) * 1009u32;
+        let n = n * ((BigUint::from(1u8) << 89) - 1u8);
+        let facts = factors_up_to(&n, 1 << 26);
+        let expected = vec![(3, 4), (1009, 1), ((1 << 24) - 3, 1), ((1 << 25) - 39, 1)];
+        assert_eq!(facts, expected);
+    }
+
     #[test]
     fn fact_pow2_works() {
         let r = 17;
@@ -229,4 +782,169 @@ mod tests {
         assert!(mrp_check(p, k, &mut rng));
         assert!(mrp_check(q, k, &mut rng));
     }
+
+    #[test]
+    fn safe_prime_works() {
+        let mut rng = rand::thread_rng();
+        let p = safe_prime(256, 10, &mut rng);
+        assert_eq!(p.bits(), 256);
+        assert!(mrp_check(&p, 10, &mut rng));
+        assert!(mrp_check(&(&p >> 1), 10, &mut rng));
+    }
+
+    #[test]
+    fn is_prime_u64_works() {
+        let primes = wheel_primes(10000);
+        for n in 0..10000u64 {
+            assert_eq!(is_prime_u64(n), primes.contains(&(n as usize)), "{n}");
+        }
+        assert!(is_prime_u64((1 << 61) - 1));
+        assert!(is_prime_u64(18446744073709551557));
+        assert!(!is_prime_u64(((1 << 32) - 5) * ((1 << 32) - 17)));
+    }
+
+    #[test]
+    fn jacobi_works() {
+        let j = |a: u32, n: u32| jacobi(&a.into(), &n.into());
+        assert_eq!(j(1001, 9907), -1);
+        assert_eq!(j(19, 45), 1);
+        assert_eq!(j(8, 21), -1);
+        assert_eq!(j(5, 21), 1);
+        assert_eq!(j(21, 35), 0);
+    }
+
+    #[test]
+    fn strong_lucas_works() {
+        // the first strong lucas pseudoprimes with selfridge's parameters
+        for n in [
+            5459u32, 5777, 10877, 16109, 18971, 22499, 24569, 25199, 40309,
+        ] {
+            assert!(strong_lucas_probable_prime(&n.into()));
+            assert!(!strong_probable_prime(&n.into(), &2u8.into()));
+        }
+        for n in (5..10000u32).step_by(2) {
+            if is_prime_u64(n as u64) {
+                assert!(strong_lucas_probable_prime(&n.into()), "{n}");
+            }
+        }
+    }
+
+    #[test]
+    fn baillie_psw_works() {
+        let primes = wheel_primes(10000);
+        for n in 0..10000u32 {
+            assert_eq!(baillie_psw(&n.into()), primes.contains(&(n as usize)));
+        }
+        let m127 = (BigUint::from(1u8) << 127) - 1u8;
+        assert!(baillie_psw(&m127));
+        assert!(!baillie_psw(&(&m127 * &m127)));
+        let mut rng = rand::thread_rng();
+        assert!(baillie_psw(&mr_prime(512, 10, &mut rng)));
+    }
+
+    #[test]
+    fn fixed_bases_are_fooled() {
+        let bases = |n: &BigUint, k: usize| {
+            wheel_primes(64)
+                .into_iter()
+                .take(k)
+                .all(|a| strong_probable_prime(n, &a.into()))
+        };
+        // the smallest strong pseudoprimes to the first k prime bases
+        let psps: [(&[u8], usize); 10] = [
+            (b"2047", 1),
+            (b"1373653", 2),
+            (b"25326001", 3),
+            (b"3215031751", 4),
+            (b"2152302898747", 5),
+            (b"3474749660383", 6),
+            (b"341550071728321", 8),
+            (b"3825123056546413051", 11),
+            (b"318665857834031151167461", 12),
+            (b"3317044064679887385961981", 13),
+        ];
+        for (n, k) in psps {
+            let n = BigUint::parse_bytes(n, 10).expect("not a number");
+            assert!(bases(&n, k));
+            assert!(!baillie_psw(&n));
+            if let Ok(n) = u64::try_from(&n) {
+                assert!(!is_prime_u64(n));
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        let (n, factors) = arnault_pseudoprime(50, &mut rng);
+        assert_eq!(factors.iter().product::<BigUint>(), n);
+        assert!(factors.iter().all(baillie_psw));
+        assert!(bases(&n, 15));
+        assert!(!baillie_psw(&n));
+        assert!(!mrp_check(&n, 10, &mut rng));
+    }
+
+    #[test]
+    fn provable_prime_works() {
+        let mut rng = rand::thread_rng();
+        for size in [40, 100, 512] {
+            let (n, cert) = provable_prime(size, &mut rng);
+            assert_eq!(n.bits(), size);
+            assert_eq!(cert.prime(), n);
+            assert!(verify_certificate(&cert));
+            assert!(baillie_psw(&n));
+        }
+
+        let (p, cert) = provable_safe_prime(256, &mut rng);
+        assert_eq!(p.bits(), 256);
+        assert!(verify_certificate(&cert));
+        let Certificate::Pocklington { factors, .. } = &cert else {
+            panic!("p is above 2^64")
+        };
+        assert_eq!(factors[0].prime(), &p >> 1);
+
+        let (p, q, cp, cq) = provable_pq(512, 160, &mut rng);
+        assert_eq!((p.bits(), q.bits()), (512, 160));
+        assert_eq!((&p - 1u8) % &q, 0u8.into());
+        assert!(verify_certificate(&cp) && verify_certificate(&cq));
+        assert_eq!((cp.prime(), cq.prime()), (p, q));
+    }
+
+    #[test]
+    fn certify_works() {
+        // 2^89 - 2 is smooth but for 2931542417
+        let m89 = (BigUint::from(1u8) << 89) - 1u8;
+        let cert = certify(&m89).expect("m89 is a prime");
+        assert!(verify_certificate(&cert));
+        assert_eq!(
+            certify(&1000003u32.into()),
+            Some(Certificate::Small(1000003))
+        );
+        assert_eq!(certify(&(&m89 * 3u8)), None);
+        assert_eq!(certify(&1000001u32.into()), None);
+    }
+
+    #[test]
+    fn forged_certificates_fail() {
+        let mut rng = rand::thread_rng();
+        let (n, cert) = provable_prime(128, &mut rng);
+        let Certificate::Pocklington {
+            witness, factors, ..
+        } = cert
+        else {
+            panic!("n is above 2^64")
+        };
+        assert!(!verify_certificate(&Certificate::Small(1000001)));
+        let forge = |n: &BigUint, witness: &BigUint, factors: &[Certificate]| {
+            verify_certificate(&Certificate::Pocklington {
+                n: n.clone(),
+                witness: witness.clone(),
+                factors: factors.to_vec(),
+            })
+        };
+        assert!(forge(&n, &witness, &factors));
+        // another n the factors do not divide
+        assert!(!forge(&(&n + 2u8), &witness, &factors));
+        // a^((n - 1) / q) = 1 for a = 1
+        assert!(!forge(&n, &1u8.into(), &factors));
+        // 2 alone is too small a part of n - 1
+        assert!(!forge(&n, &witness, &[Certificate::Small(2)]));
+    }
 }
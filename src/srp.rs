@@ -1,6 +1,8 @@
+use crate::ct::{self, Secret};
 use crate::dh;
-use crate::digest::{hmac, sha256};
+use crate::digest::{hmac, sha256, Sha256};
 use crate::encode::from_ascii;
+use crate::kdf::{pbkdf2, scrypt};
 use num_bigint::BigUint;
 use rand::Rng;
 use std::collections::HashMap;
@@ -37,14 +39,14 @@ fn p() -> BigUint {
 }
 
 pub struct Server {
-    privkey: BigUint,
+    privkey: Secret<BigUint>,
     credentials: HashMap<String, (BigUint, u128)>,
 }
 
 impl Server {
     pub fn init() -> Self {
         Self {
-            privkey: dh::dh_keys(&p(), &g(), &mut rand::thread_rng()).1,
+            privkey: Secret::new(dh::dh_keys(&p(), &g(), &mut rand::thread_rng()).1),
             credentials: HashMap::new(),
         }
     }
@@ -56,7 +58,7 @@ impl Server {
     pub fn identify(&self, email: &str) -> (BigUint, u128) {
         let (v, s) = self.credentials.get(email).expect("client not registered");
         (
-            (modsum(modmult(k(), v), &g().modpow(&self.privkey, &p()))),
+            (modsum(modmult(k(), v), &g().modpow(self.privkey.expose(), &p()))),
             *s,
         )
     }
@@ -67,32 +69,54 @@ impl Server {
         BigUint::from_bytes_be(&sha256(&pubkeys))
     }
 
-    pub fn validate_srp(&self, email: &str, pubkey: &BigUint, digest: [u8; 32]) -> bool {
+    fn expected_digest(&self, email: &str, pubkey: &BigUint) -> [u8; 32] {
         let p = p();
         let u = self.calc_u(email, pubkey);
         let (v, salt) = self.credentials.get(email).expect("client not registered");
-        let s = modmult(v.clone().modpow(&u, &p), pubkey).modpow(&self.privkey, &p);
+        let s = modmult(v.clone().modpow(&u, &p), pubkey).modpow(self.privkey.expose(), &p);
         let k = sha256(&s.to_bytes_be());
+        hmac::<Sha256, 32>(&k, &salt.to_be_bytes())
+    }
 
-        let res = hmac(&k, &salt.to_be_bytes(), sha256);
-        res == digest
+    pub fn validate_srp(&self, email: &str, pubkey: &BigUint, digest: [u8; 32]) -> bool {
+        ct::eq(&self.expected_digest(email, pubkey), &digest)
     }
+
+    // stops at the first byte that differs, leaking through its timing how
+    // much of a forged digest is right
+    pub fn validate_srp_insecure(&self, email: &str, pubkey: &BigUint, digest: [u8; 32]) -> bool {
+        self.expected_digest(email, pubkey) == digest
+    }
+}
+
+// how the client derives x from the salt and the password
+#[derive(Clone, Copy, Debug)]
+pub enum PasswordKdf {
+    Sha256,
+    Pbkdf2 { iterations: u32 },
+    Scrypt { log_n: u8, r: usize, p: usize },
 }
 
 pub struct Client {
     email: String,
     password: String,
-    privkey: BigUint,
+    kdf: PasswordKdf,
+    privkey: Secret<BigUint>,
     pubkey: BigUint,
 }
 
 impl Client {
     pub fn init(email: String, password: String) -> Self {
+        Self::init_with_kdf(email, password, PasswordKdf::Sha256)
+    }
+
+    pub fn init_with_kdf(email: String, password: String, kdf: PasswordKdf) -> Self {
         let (pubkey, privkey) = dh::dh_keys(&p(), &g(), &mut rand::thread_rng());
         Self {
             email,
             password,
-            privkey,
+            kdf,
+            privkey: Secret::new(privkey),
             pubkey,
         }
     }
@@ -107,9 +131,16 @@ impl Client {
     }
 
     fn gen_key(&self, salt: u128) -> BigUint {
-        let mut x_h = Vec::from(salt.to_be_bytes());
-        x_h.extend(from_ascii(&self.password));
-        BigUint::from_bytes_be(&sha256(&x_h))
+        let salt = salt.to_be_bytes();
+        let password = from_ascii(&self.password);
+        let x_h = match self.kdf {
+            PasswordKdf::Sha256 => Vec::from(sha256(&[&salt[..], &password].concat())),
+            PasswordKdf::Pbkdf2 { iterations } => {
+                pbkdf2::<Sha256, 32>(&password, &salt, iterations, 32)
+            }
+            PasswordKdf::Scrypt { log_n, r, p } => scrypt(&password, &salt, log_n, r, p, 32),
+        };
+        BigUint::from_bytes_be(&x_h)
     }
 
     pub fn register(&self, server: &mut Server) {
@@ -129,15 +160,15 @@ impl Client {
         let u = self.calc_u(&server_pubkey);
         let x = self.gen_key(salt);
         let neg_k_gx = modmult(p() - g().modpow(&x, &p()), &k());
-        let exp = modsum(u * x, &self.privkey);
+        let exp = modsum(u * x, self.privkey.expose());
         let s = modsum(neg_k_gx, server_pubkey).modpow(&exp, &p());
         let k = &sha256(&s.to_bytes_be());
-        hmac(k, &salt.to_be_bytes(), sha256)
+        hmac::<Sha256, 32>(k, &salt.to_be_bytes())
     }
 
     pub fn get_zero_digest(&self, salt: u128) -> [u8; 32] {
         let k0 = &sha256(&0u8.to_be_bytes());
-        hmac(k0, &salt.to_be_bytes(), sha256)
+        hmac::<Sha256, 32>(k0, &salt.to_be_bytes())
     }
 }
 
@@ -156,12 +187,14 @@ mod tests {
         let (server_pubkey1, salt1) = server.identify(email1);
         let digest1 = client1.get_digest(&server_pubkey1, salt1);
         assert!(server.validate_srp(email1, pubkey1, digest1));
+        assert!(server.validate_srp_insecure(email1, pubkey1, digest1));
 
         // client2 tries to auth as client1
         let (email2, pubkey2) = client1.identify();
         let (server_pubkey2, salt2) = server.identify(&email2);
         let digest2 = client2.get_digest(&server_pubkey2, salt2);
         assert!(!server.validate_srp(email2, pubkey2, digest2));
+        assert!(!server.validate_srp_insecure(email2, pubkey2, digest2));
 
         // client2 auths as client1
         let email0 = email1;
@@ -169,6 +202,36 @@ mod tests {
         let (_, salt0) = server.identify(email0);
         let digest0 = client2.get_zero_digest(salt0);
         assert!(server.validate_srp(email0, pubkey0, digest0));
+        assert!(server.validate_srp_insecure(email0, pubkey0, digest0));
         //assert!(server.validate_srp(email0, &p(), digest0));
     }
+
+    #[test]
+    fn auth_works_with_kdf() {
+        let mut server = Server::init();
+        let kdfs = [
+            PasswordKdf::Pbkdf2 { iterations: 1000 },
+            PasswordKdf::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: 1,
+            },
+        ];
+        for (i, kdf) in kdfs.into_iter().enumerate() {
+            let email = format!("email{}", i);
+            let client = Client::init_with_kdf(email.clone(), String::from("pass"), kdf);
+            client.register(&mut server);
+            let (email, pubkey) = client.identify();
+            let (server_pubkey, salt) = server.identify(email);
+            let digest = client.get_digest(&server_pubkey, salt);
+            assert!(server.validate_srp(email, pubkey, digest));
+
+            // same password, different kdf
+            let other = Client::init(String::from(email), String::from("pass"));
+            let (email, pubkey) = other.identify();
+            let (server_pubkey, salt) = server.identify(email);
+            let digest = other.get_digest(&server_pubkey, salt);
+            assert!(!server.validate_srp(email, pubkey, digest));
+        }
+    }
 }
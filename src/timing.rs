@@ -0,0 +1,120 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    for (x, y) in a.iter().zip(b) {
+        if x != y {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+    true
+}
+
+// timing statistic for each candidate of mac[pos], candidates are interleaved to even out drift
+fn time_candidates<const N: usize>(
+    query: &impl Fn(&[u8; N]) -> bool,
+    mac: &mut [u8; N],
+    pos: usize,
+    samples: usize,
+    stat: &impl Fn(&mut [Duration]) -> Duration,
+) -> Vec<(Duration, u8)> {
+    let mut times = vec![vec![]; 256];
+    for _ in 0..samples {
+        for b in u8::MIN..=u8::MAX {
+            mac[pos] = b;
+            let start = Instant::now();
+            query(mac);
+            times[b as usize].push(start.elapsed());
+        }
+    }
+    let mut res: Vec<_> = (u8::MIN..=u8::MAX)
+        .map(|b| (stat(&mut times[b as usize]), b))
+        .collect();
+    res.sort_by(|a, b| b.cmp(a));
+    res
+}
+
+// the slowest candidate, its lead over the median and the lead of the runner-up
+fn slowest<const N: usize>(
+    query: &impl Fn(&[u8; N]) -> bool,
+    mac: &mut [u8; N],
+    pos: usize,
+    samples: usize,
+    stat: &impl Fn(&mut [Duration]) -> Duration,
+) -> (u8, Duration, Duration) {
+    let ts = time_candidates(query, mac, pos, samples, stat);
+    let med = ts[ts.len() / 2].0;
+    (ts[0].1, ts[0].0 - med, ts[1].0 - med)
+}
+
+pub fn recover_mac<const N: usize>(
+    query: impl Fn(&[u8; N]) -> bool,
+    samples: usize,
+    max_samples: usize,
+    stat: impl Fn(&mut [Duration]) -> Duration,
+) -> Option<[u8; N]> {
+    let mut mac = [0; N];
+    let mut samples_at = [samples; N];
+    // the extra time a correct byte takes, once it has been measured
+    let mut step: Option<Duration> = None;
+    let mut backtracks = 0;
+    let mut pos = 0;
+    // the last byte is found from the response instead of the timing
+    while pos + 1 < N {
+        let (b, lead, lead2) = slowest(&query, &mut mac, pos, samples_at[pos], &stat);
+        let clear = match step {
+            Some(s) => lead > s / 2 && lead2 < s / 2,
+            None => lead - lead2 > lead2,
+        };
+        if clear {
+            step = Some(step.map_or(lead, |s| s.min(lead)));
+            mac[pos] = b;
+            pos += 1;
+        } else if samples_at[pos] < max_samples {
+            samples_at[pos] *= 2;
+        } else if pos > 0 && backtracks < N {
+            // nothing stands out so the previous byte is likely wrong
+            backtracks += 1;
+            samples_at[pos] = samples;
+            pos -= 1;
+            samples_at[pos] = max_samples;
+        } else {
+            mac[pos] = b;
+            pos += 1;
+        }
+    }
+    (u8::MIN..=u8::MAX).find_map(|b| {
+        mac[N - 1] = b;
+        query(&mac).then_some(mac)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dist::{median, trimmed_mean};
+    use rand::Rng;
+
+    #[test]
+    fn insecure_compare_works() {
+        let delay = Duration::from_millis(1);
+        assert!(insecure_compare(&[1, 2, 3], &[1, 2, 3], delay));
+        assert!(!insecure_compare(&[1, 2, 3], &[1, 2, 4], delay));
+        assert!(!insecure_compare(&[1, 2], &[1, 2, 3], delay));
+    }
+
+    #[test]
+    fn recover_mac_works() {
+        let mut rng = rand::thread_rng();
+        let delay = Duration::from_micros(500);
+        let mac: [u8; 4] = rng.gen();
+        let query = |guess: &[u8; 4]| insecure_compare(guess, &mac, delay);
+        assert_eq!(recover_mac(query, 1, 8, median), Some(mac));
+        let stat = |v: &mut [Duration]| trimmed_mean(v, 0.25);
+        assert_eq!(recover_mac(query, 3, 12, stat), Some(mac));
+    }
+}
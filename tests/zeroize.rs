@@ -0,0 +1,80 @@
+// a binary of its own, so only these tests run on the watching allocator
+use cryptopals::ct::{Secret, Zeroize};
+use num_bigint::RandBigInt;
+use rand::Rng;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static WATCHING: Cell<bool> = const { Cell::new(false) };
+    static LEAKED: Cell<bool> = const { Cell::new(false) };
+}
+
+// while a thread is watching, notes any block it frees or moves that still
+// holds something other than zeros
+struct Watch;
+
+fn check(ptr: *mut u8, size: usize) {
+    if WATCHING.try_with(|w| w.get()).unwrap_or(false) {
+        let block = unsafe { std::slice::from_raw_parts(ptr, size) };
+        if block.iter().any(|b| *b != 0) {
+            LEAKED.with(|l| l.set(true));
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for Watch {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        check(ptr, layout.size());
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        check(ptr, layout.size());
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static WATCH: Watch = Watch;
+
+fn watch(f: impl FnOnce()) -> bool {
+    WATCHING.with(|w| w.set(true));
+    f();
+    WATCHING.with(|w| w.set(false));
+    LEAKED.with(|l| l.replace(false))
+}
+
+#[test]
+fn zeroize_secret_biguint_works() {
+    let mut rng = rand::thread_rng();
+    for bits in [64, 65, 1024, 4096] {
+        // the shift moves the digits down in the same buffer and leaves the old
+        // top ones past the length, as arithmetic does
+        let mut x = rng.gen_biguint(2 * bits) >> bits;
+        x.set_bit(bits - 1, true);
+        let secret = Secret::new(x);
+        assert_eq!(secret.expose().bits(), bits);
+        assert!(
+            !watch(|| drop(secret)),
+            "digits freed before they were cleared"
+        );
+    }
+}
+
+#[test]
+fn zeroize_vec_works() {
+    let mut rng = rand::thread_rng();
+    let mut v: Vec<u8> = (0..64).map(|_| rng.gen_range(1..=255)).collect();
+    v.truncate(16);
+    assert!(!watch(|| {
+        v.zeroize();
+        drop(v)
+    }));
+    let secret = Secret::new(vec![7u8; 32]);
+    assert!(!watch(|| drop(secret)));
+}